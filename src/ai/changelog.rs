@@ -0,0 +1,73 @@
+use colored::Colorize;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::ai::{get_command_message, git, require_text_output, theme};
+use crate::changelog::{self, Entry};
+use crate::conventional::ConventionalCommit;
+use crate::{args, llm};
+
+/// Writes the changelog of the commits of `<from>..<to>`, `<to>` defaults to HEAD.
+///
+/// The release is printed, or put in front of the releases of the `prepend` file. Commits not following
+/// Conventional Commits are left out.
+pub fn handler(range: &str, polish: bool, prepend: Option<&Path>, args: &args::CommandArgs) -> anyhow::Result<()> {
+    require_text_output("changelog", args)?;
+    if !git::is_git_directory() {
+        eprintln!("Not git directory");
+        return Ok(());
+    }
+
+    let (from, to) = range.split_once("..").unwrap_or((range, ""));
+    if to.starts_with('.') {
+        return Err(anyhow::anyhow!("symmetric ranges are not supported: {}", range));
+    }
+    let to = if to.is_empty() { "HEAD" } else { to };
+
+    let commits = git::git_range_commits(&format!("{}..{}", from, to))?;
+    let total = commits.len();
+    let entries: Vec<Entry> = commits
+        .into_iter()
+        .filter_map(|(hash, message)| ConventionalCommit::parse(&message).map(|commit| Entry { hash, commit }))
+        .collect();
+    if total > entries.len() {
+        eprintln!(
+            "{} Skipped {} commits not following Conventional Commits",
+            "⚠️".bright_yellow(),
+            total - entries.len()
+        );
+    }
+    if entries.is_empty() {
+        eprintln!("No commits in {}..{}", from, to);
+        return Ok(());
+    }
+
+    let version = git::git_tag_of(to);
+    let mut release = changelog::render(version.as_ref().map(|(tag, date)| (tag.as_str(), date.as_str())), &entries);
+    if polish {
+        println!("{}", get_command_message());
+        let start = Instant::now();
+        let (polished, usage) = llm::llm_polish_changelog(&release, args)?;
+        theme::print_stats(&usage, start.elapsed());
+        release = polished;
+    }
+
+    match prepend {
+        Some(path) => {
+            let existing = match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(anyhow::anyhow!("cannot read {}: {}", path.display(), e)),
+            };
+            let heading = release.lines().next().unwrap_or_default();
+            if existing.lines().any(|line| line == heading) {
+                return Err(anyhow::anyhow!("{} already contains {}", path.display(), heading));
+            }
+            std::fs::write(path, changelog::prepend(&existing, &release))
+                .map_err(|e| anyhow::anyhow!("cannot write {}: {}", path.display(), e))?;
+            println!("{} {}", "Updated".green(), path.display());
+        }
+        None => print!("{}", release),
+    }
+    Ok(())
+}
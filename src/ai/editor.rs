@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::process::Command;
+
+use crate::ai::git;
+use crate::conventional::lint::strip_comments;
+
+/// Opens a commit message in the editor of git, with the files of the commit listed in comments the way git does.
+///
+/// The message is edited in `COMMIT_EDITMSG` of the git directory, like `git commit` does.
+///
+/// Returns the edited message without comments, `None` when it was emptied.
+pub fn edit_message(message: &str, filenames: &[String]) -> Result<Option<String>> {
+    let path = git::git_dir()?.join("COMMIT_EDITMSG");
+    let comment = git::git_comment_prefix();
+    fs::write(&path, template(message, filenames, &comment))?;
+
+    let editor = editor();
+    // the editor may hold arguments, e.g. `code --wait`, so it is run by the shell like git does
+    let status = Command::new("sh")
+        .args(["-c", &format!("{} \"$@\"", editor), &editor])
+        .arg(&path)
+        .status();
+    let edited = fs::read_to_string(&path);
+
+    if !status.as_ref().is_ok_and(|s| s.success()) {
+        return Err(anyhow!("there was a problem with the editor `{}`", editor));
+    }
+    let message = strip_comments(&edited?, &comment);
+    Ok(Some(message).filter(|m| !m.is_empty()))
+}
+
+/// `$GIT_EDITOR`, then `core.editor`, then `$VISUAL`, then `$EDITOR`, then vi, in the order of git
+fn editor() -> String {
+    let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    env("GIT_EDITOR")
+        .or_else(|| git::git_config("core.editor"))
+        .or_else(|| env("VISUAL"))
+        .or_else(|| env("EDITOR"))
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// The message followed by the comments of git, starting with `comment`
+fn template(message: &str, filenames: &[String], comment: &str) -> String {
+    let mut content = format!(
+        "{}\n\n\
+        {comment} Please enter the commit message for your changes. Lines starting\n\
+        {comment} with '{comment}' will be ignored, and an empty message aborts the commit.\n",
+        message.trim_end()
+    );
+    if !filenames.is_empty() {
+        content.push_str(&format!("{comment}\n{comment} Changes to be committed:\n"));
+        for filename in filenames {
+            content.push_str(&format!("{comment}\t{}\n", filename));
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_template() {
+        let content = template("feat: add b\n\nbody\n", &["a.txt".to_string(), "b.txt".to_string()], "#");
+        assert_eq!(
+            content,
+            "feat: add b\n\nbody\n\n\
+            # Please enter the commit message for your changes. Lines starting\n\
+            # with '#' will be ignored, and an empty message aborts the commit.\n\
+            #\n# Changes to be committed:\n#\ta.txt\n#\tb.txt\n"
+        );
+        assert_eq!(strip_comments(&content, "#"), "feat: add b\n\nbody");
+
+        let content = template("feat: add b\n\n#12 is fixed", &["a.txt".to_string()], ";");
+        assert!(content.ends_with(
+            "; with ';' will be ignored, and an empty message aborts the commit.\n\
+            ;\n; Changes to be committed:\n;\ta.txt\n"
+        ));
+        assert_eq!(strip_comments(&content, ";"), "feat: add b\n\n#12 is fixed");
+    }
+}
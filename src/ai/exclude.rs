@@ -0,0 +1,118 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use crate::ai::git::StagedFile;
+
+/// Ignore file in the root of the working tree, in gitignore syntax
+pub const IGNORE_FILE_NAME: &str = ".gitbuddyignore";
+
+/// Staged files left out of the diff: the configured `ignore` patterns and `.gitbuddyignore`
+pub struct Exclusions {
+    matcher: Gitignore,
+}
+
+impl Exclusions {
+    /// Builds the matcher, invalid patterns are reported and skipped
+    ///
+    /// # Arguments
+    /// * `root` - The root of the working tree, holding `.gitbuddyignore`
+    /// * `patterns` - Patterns from the configuration, in gitignore syntax
+    pub fn new(root: &Path, patterns: &[String]) -> Exclusions {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                eprintln!("Invalid ignore pattern: {}", e);
+            }
+        }
+        let file = root.join(IGNORE_FILE_NAME);
+        if file.is_file() {
+            if let Some(e) = builder.add(&file) {
+                eprintln!("Invalid {}: {}", IGNORE_FILE_NAME, e);
+            }
+        }
+
+        let matcher = builder.build().unwrap_or_else(|e| {
+            eprintln!("Invalid ignore patterns: {}", e);
+            Gitignore::empty()
+        });
+        Exclusions { matcher }
+    }
+
+    /// Whether the path, relative to the root of the working tree, is left out of the diff
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.matcher.matched_path_or_any_parents(path, false).is_ignore()
+    }
+}
+
+impl Display for StagedFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let status = match self.status {
+            'A' => "added",
+            'D' => "deleted",
+            'T' => "type changed",
+            _ => "modified",
+        };
+        match self.lines {
+            Some((added, deleted)) => write!(f, "{}: {}, +{} -{}", status, self.path, added, deleted),
+            None => write!(f, "{}: {}, binary", status, self.path),
+        }
+    }
+}
+
+/// One-line summaries of the excluded files, put in front of the diff so the model still knows about them
+pub fn summarize_excluded(files: &[StagedFile]) -> String {
+    if files.is_empty() {
+        return String::new();
+    }
+    let mut summary = String::from("# Excluded from the diff:\n");
+    for file in files {
+        summary.push_str(&format!("# {}\n", file));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_exclusions() {
+        let root = std::env::temp_dir().join("gitbuddy-exclusions");
+        let exclusions = Exclusions::new(&root, &strings(&["*.lock", "!keep.lock", "node_modules", "/dist", "docs/**/*.png"]));
+
+        assert!(exclusions.is_excluded("yarn.lock"));
+        assert!(exclusions.is_excluded("web/yarn.lock"));
+        assert!(!exclusions.is_excluded("keep.lock"));
+        assert!(exclusions.is_excluded("web/node_modules/react/index.js"));
+        assert!(exclusions.is_excluded("dist/app.js"));
+        assert!(!exclusions.is_excluded("web/dist/app.js"));
+        assert!(exclusions.is_excluded("docs/img/logo.png"));
+        assert!(!exclusions.is_excluded("src/main.rs"));
+    }
+
+    #[test]
+    fn test_summarize_excluded() {
+        let files = [
+            StagedFile {
+                status: 'M',
+                path: "yarn.lock".to_string(),
+                lines: Some((340, 290)),
+            },
+            StagedFile {
+                status: 'A',
+                path: "logo.png".to_string(),
+                lines: None,
+            },
+        ];
+        assert_eq!(
+            summarize_excluded(&files),
+            "# Excluded from the diff:\n# modified: yarn.lock, +340 -290\n# added: logo.png, binary\n"
+        );
+        assert_eq!(summarize_excluded(&[]), "");
+    }
+}
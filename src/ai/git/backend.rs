@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+use crate::ai::git::cli::CliBackend;
+use crate::ai::git::libgit::LibGitBackend;
+
+/// A staged file and its line counts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedFile {
+    /// Status letter as in `git diff --name-status`, e.g. `M`
+    pub status: char,
+    /// Path relative to the root of the working tree
+    pub path: String,
+    /// Added and deleted lines, `None` for binary files
+    pub lines: Option<(u64, u64)>,
+}
+
+/// Access to the repository, either in-process or through the `git` command
+pub trait GitBackend {
+    /// Name of the backend, shown in error messages
+    fn name(&self) -> &'static str;
+
+    /// Root directory of the working tree
+    fn toplevel(&self) -> &Path;
+
+    /// Staged files compared to HEAD, renames are listed as a deletion and an addition
+    fn staged_files(&self) -> Result<Vec<StagedFile>>;
+
+    /// Staged diff compared to HEAD, leaving out the `excludes` paths relative to the root of the working tree.
+    /// Content which is not valid UTF-8 is replaced lossily.
+    fn staged_diff(&self, excludes: &[String]) -> Result<String>;
+
+    /// Files changed from `from` to `to`, renames are listed as a deletion and an addition.
+    /// `from` is the empty tree when `None`, `to` is the index when `None`.
+    fn changed_files(&self, from: Option<&str>, to: Option<&str>) -> Result<Vec<StagedFile>>;
+
+    /// Diff from `from` to `to`, as in [`GitBackend::changed_files`], leaving out the `excludes` paths relative to
+    /// the root of the working tree. Content which is not valid UTF-8 is replaced lossily.
+    fn diff(&self, from: Option<&str>, to: Option<&str>, excludes: &[String]) -> Result<String>;
+
+    /// Id of the best common ancestor of two commits
+    fn merge_base(&self, one: &str, two: &str) -> Result<String>;
+
+    /// Id of the first parent of a commit, `None` for a root commit
+    fn first_parent(&self, rev: &str) -> Result<Option<String>>;
+
+    /// Creates a commit of the index on top of HEAD
+    fn commit(&self, message: &str) -> Result<()>;
+
+    /// Replaces HEAD by a commit of the index with a new message, keeping the author of HEAD
+    fn amend(&self, message: &str) -> Result<()>;
+}
+
+/// Opens the repository containing `path`.
+///
+/// The in-process backend is preferred, so that no `git` executable is needed. The `git` command is the fallback
+/// for repositories libgit2 cannot open, e.g. with newer repository extensions.
+pub fn open(path: &Path) -> Result<Box<dyn GitBackend>> {
+    let libgit_error = match LibGitBackend::discover(path) {
+        Ok(backend) => return Ok(Box::new(backend)),
+        Err(e) => e,
+    };
+    match CliBackend::discover(path) {
+        Ok(backend) => Ok(Box::new(backend)),
+        Err(cli_error) => Err(anyhow!("Not git directory: {} ({})", libgit_error, cli_error)),
+    }
+}
+
+/// Opens the repository of the current directory, see [`open`]
+pub fn current() -> Result<Box<dyn GitBackend>> {
+    let dir: PathBuf = std::env::current_dir()?;
+    open(&dir)
+}
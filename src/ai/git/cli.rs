@@ -0,0 +1,310 @@
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use crate::ai::git::backend::{GitBackend, StagedFile};
+
+/// The empty tree, compared to root commits
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Backend running the `git` command, the fallback of the in-process backend
+pub struct CliBackend {
+    root: PathBuf,
+}
+
+impl CliBackend {
+    /// Finds the working tree containing `path`, fails outside a repository or without `git` installed
+    pub fn discover(path: &Path) -> Result<CliBackend> {
+        let output = Command::new("git")
+            .current_dir(path)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .map_err(|e| anyhow!("git is not available: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(CliBackend {
+            root: PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()),
+        })
+    }
+
+    /// Files changed between `revs`, as given to `git diff`, renames are listed as a deletion and an addition
+    fn diff_files(&self, revs: &[&str]) -> Result<Vec<StagedFile>> {
+        let run = |format: &str| -> Result<String> {
+            let mut args = vec!["diff", "--no-ext-diff", "--no-renames", "-z", format];
+            args.extend_from_slice(revs);
+            let output = self.git(args)?;
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+
+        // numstat entries are "<added>\t<deleted>\t<path>", "-" counts for binary files
+        let mut lines = HashMap::new();
+        for entry in run("--numstat")?.split('\0').filter(|s| !s.is_empty()) {
+            let mut fields = entry.splitn(3, '\t');
+            if let (Some(added), Some(deleted), Some(path)) = (fields.next(), fields.next(), fields.next()) {
+                lines.insert(path.to_string(), added.parse().ok().zip(deleted.parse().ok()));
+            }
+        }
+
+        // name-status entries are "<status>\0<path>"
+        let name_status = run("--name-status")?;
+        let mut fields = name_status.split('\0').filter(|s| !s.is_empty());
+        let mut files = Vec::new();
+        while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+            files.push(StagedFile {
+                status: status.chars().next().unwrap_or('M'),
+                path: path.to_string(),
+                lines: lines.get(path).copied().flatten(),
+            });
+        }
+        Ok(files)
+    }
+
+    /// Diff between `revs`, as given to `git diff`, leaving out the `excludes` paths
+    fn diff_content(&self, revs: &[&str], excludes: &[String]) -> Result<String> {
+        let mut args: Vec<String> = vec!["diff".to_string(), "--no-ext-diff".to_string()];
+        args.push("--diff-algorithm=minimal".to_string());
+        args.extend(revs.iter().map(|r| r.to_string()));
+        if !excludes.is_empty() {
+            // a pathspec made of excludes only still matches everything else
+            args.push("--".to_string());
+            args.extend(excludes.iter().map(|path| format!(":(top,exclude,literal){}", path)));
+        }
+        let output = self.git(args)?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Fails when any of the commits of the current branch is already on its upstream branch
+    pub fn check_unpublished(&self, commits: &[String]) -> Result<()> {
+        let Ok(upstream) = self.git(["rev-parse", "--verify", "-q", "@{upstream}"]) else {
+            return Ok(());
+        };
+        let upstream = String::from_utf8_lossy(&upstream.stdout).trim().to_string();
+        let ids = self.resolve_commits(commits)?;
+
+        // the commits of the branch which are not on the upstream branch, in one walk
+        let output = self.git(["rev-list", "HEAD", "--not", upstream.as_str()])?;
+        let unpublished: HashSet<&str> = std::str::from_utf8(&output.stdout)?.lines().collect();
+        let published: Vec<&str> = commits
+            .iter()
+            .zip(&ids)
+            .filter(|(_, id)| !unpublished.contains(id.as_str()))
+            .map(|(commit, _)| commit.as_str())
+            .collect();
+        if !published.is_empty() {
+            return Err(anyhow!(
+                "already on the upstream branch, refusing to rewrite: {}",
+                published.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Rewrites the messages of commits of the current branch, recreating the commits on top of them with
+    /// their trees, authors and messages unchanged. HEAD is moved last.
+    pub fn rewrite_messages(&self, messages: &[(String, String)], dry_run: bool) -> Result<()> {
+        let revs: Vec<String> = messages.iter().map(|(rev, _)| rev.clone()).collect();
+        let ids = self.resolve_commits(&revs)?;
+        let new_messages: HashMap<&str, &str> = ids
+            .iter()
+            .map(String::as_str)
+            .zip(messages.iter().map(|(_, message)| message.as_str()))
+            .collect();
+
+        let mut walk = self.walk_down_to(&new_messages.keys().copied().collect::<Vec<_>>())?;
+        if let Some(missing) = ids.iter().position(|id| !walk.iter().any(|(commit, _)| commit == id)) {
+            return Err(anyhow!("{} is not a commit of the current branch", revs[missing]));
+        }
+        if dry_run || new_messages.is_empty() {
+            return Ok(());
+        }
+
+        // parents first, a commit is recreated when it is reworded or when one of its parents was
+        walk.reverse();
+        let mut rewritten: HashMap<String, String> = HashMap::new();
+        for (commit, parents) in &walk {
+            let new_parents: Vec<&str> = parents
+                .iter()
+                .map(|p| rewritten.get(p).map_or(p.as_str(), String::as_str))
+                .collect();
+            let message = new_messages.get(commit.as_str()).copied();
+            if message.is_none() && new_parents.iter().eq(parents.iter()) {
+                continue;
+            }
+            let new_commit = self.recreate_commit(commit, &new_parents, message)?;
+            rewritten.insert(commit.clone(), new_commit);
+        }
+
+        let head = &walk.last().ok_or_else(|| anyhow!("HEAD was not rewritten"))?.0;
+        let new_head = rewritten.get(head).ok_or_else(|| anyhow!("HEAD was not rewritten"))?;
+        self.git(["update-ref", "-m", "gitbuddy: reword", "HEAD", new_head, head])?;
+        Ok(())
+    }
+
+    /// Full ids of the commits of `revs`, in one `rev-parse`
+    fn resolve_commits(&self, revs: &[String]) -> Result<Vec<String>> {
+        if revs.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut args = vec!["rev-parse".to_string()];
+        args.extend(revs.iter().map(|rev| format!("{}^{{commit}}", rev)));
+        let output = self.git(args)?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// The commits from HEAD down to the last of `commits` met, children before their parents, with their parents.
+    ///
+    /// The walk stops there: in topological order every commit on top of `commits` has been listed by then.
+    fn walk_down_to(&self, commits: &[&str]) -> Result<Vec<(String, Vec<String>)>> {
+        let mut remaining: HashSet<&str> = commits.iter().copied().collect();
+        let mut walk = Vec::new();
+        if remaining.is_empty() {
+            return Ok(walk);
+        }
+
+        let mut child = Command::new("git")
+            .current_dir(&self.root)
+            .args(["rev-list", "--topo-order", "--parents", "HEAD"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            let mut ids = line.split(' ').map(str::to_string);
+            let Some(commit) = ids.next() else { continue };
+            remaining.remove(commit.as_str());
+            walk.push((commit, ids.collect()));
+            if remaining.is_empty() {
+                break;
+            }
+        }
+        // the rest of the history is not needed
+        let _ = child.kill();
+        let _ = child.wait();
+        Ok(walk)
+    }
+
+    /// Creates a copy of a commit with other parents, and a new message or the original one byte for byte
+    fn recreate_commit(&self, commit: &str, parents: &[&str], message: Option<&str>) -> Result<String> {
+        let author = self.git(["log", "-1", "--format=%an%x00%ae%x00%ad", "--date=raw", commit])?;
+        let author = String::from_utf8_lossy(&author.stdout);
+        let [name, email, date] = author.trim_end_matches('\n').split('\0').collect::<Vec<_>>()[..] else {
+            return Err(anyhow!("cannot read the author of {}", commit));
+        };
+        let message = match message {
+            Some(message) => format!("{}\n", message.trim_end()).into_bytes(),
+            // the raw message, after the headers of the commit object
+            None => {
+                let object = self.git(["cat-file", "commit", commit])?.stdout;
+                let start = object
+                    .windows(2)
+                    .position(|w| w == b"\n\n")
+                    .map_or(object.len(), |i| i + 2);
+                object[start..].to_vec()
+            }
+        };
+
+        let tree = format!("{}^{{tree}}", commit);
+        let mut args = vec!["commit-tree", tree.as_str()];
+        for parent in parents {
+            args.extend(["-p", parent]);
+        }
+        args.extend(["-F", "-"]);
+        let mut child = Command::new("git")
+            .current_dir(&self.root)
+            .args(&args)
+            .envs([
+                ("GIT_AUTHOR_NAME", name),
+                ("GIT_AUTHOR_EMAIL", email),
+                ("GIT_AUTHOR_DATE", date),
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child.stdin.take().expect("stdin is piped").write_all(&message)?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn git<I, S>(&self, args: I) -> Result<Output>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let output = Command::new("git").current_dir(&self.root).args(args).output()?;
+        if !output.status.success() {
+            return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(output)
+    }
+}
+
+impl GitBackend for CliBackend {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn toplevel(&self) -> &Path {
+        &self.root
+    }
+
+    fn staged_files(&self) -> Result<Vec<StagedFile>> {
+        self.diff_files(&["--cached"])
+    }
+
+    fn staged_diff(&self, excludes: &[String]) -> Result<String> {
+        self.diff_content(&["--cached"], excludes)
+    }
+
+    fn changed_files(&self, from: Option<&str>, to: Option<&str>) -> Result<Vec<StagedFile>> {
+        self.diff_files(&diff_revs(from, to))
+    }
+
+    fn diff(&self, from: Option<&str>, to: Option<&str>, excludes: &[String]) -> Result<String> {
+        self.diff_content(&diff_revs(from, to), excludes)
+    }
+
+    fn merge_base(&self, one: &str, two: &str) -> Result<String> {
+        let output = self.git(["merge-base", one, two])?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn first_parent(&self, rev: &str) -> Result<Option<String>> {
+        // "<commit> <parent>...", a root commit has no parent
+        let output = self.git(["rev-list", "--parents", "-n1", &format!("{}^{{commit}}", rev), "--"])?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .nth(1)
+            .map(str::to_string))
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        self.git(["commit", "-m", message])?;
+        Ok(())
+    }
+
+    fn amend(&self, message: &str) -> Result<()> {
+        self.git(["commit", "--amend", "-m", message])?;
+        Ok(())
+    }
+}
+
+/// Arguments of `git diff` comparing `from` to `to`, see [`GitBackend::changed_files`]
+fn diff_revs<'a>(from: Option<&'a str>, to: Option<&'a str>) -> Vec<&'a str> {
+    let from = from.unwrap_or(EMPTY_TREE);
+    match to {
+        Some(to) => vec![from, to],
+        None => vec!["--cached", from],
+    }
+}
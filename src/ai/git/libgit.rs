@@ -0,0 +1,218 @@
+use anyhow::{anyhow, Result};
+use git2::{Delta, Diff, DiffFindOptions, DiffOptions, Patch, Repository, Tree};
+use std::path::{Path, PathBuf};
+
+use crate::ai::git::backend::{GitBackend, StagedFile};
+use crate::ai::git::cli::CliBackend;
+
+/// Hooks run by `git commit`, libgit2 does not run them
+const COMMIT_HOOKS: [&str; 4] = ["pre-commit", "prepare-commit-msg", "commit-msg", "post-commit"];
+
+/// In-process backend on top of libgit2, needs no `git` executable
+pub struct LibGitBackend {
+    repo: Repository,
+    root: PathBuf,
+}
+
+impl LibGitBackend {
+    /// Finds the working tree containing `path`, bare repositories are rejected
+    pub fn discover(path: &Path) -> Result<LibGitBackend> {
+        let repo = Repository::discover(path)?;
+        let root = repo
+            .workdir()
+            .ok_or_else(|| anyhow!("bare repository has no working tree"))?
+            .to_path_buf();
+        Ok(LibGitBackend { repo, root })
+    }
+
+    fn head_tree(&self) -> Option<Tree<'_>> {
+        self.repo.head().ok().and_then(|head| head.peel_to_tree().ok())
+    }
+
+    /// Tree of a commit or tree given as a revision
+    fn tree(&self, rev: &str) -> Result<Tree<'_>> {
+        Ok(self.repo.revparse_single(rev)?.peel_to_tree()?)
+    }
+
+    /// Diff of the index against HEAD, renames are only detected when `renames` is set
+    fn index_diff(&self, renames: bool) -> Result<Diff<'_>> {
+        self.tree_diff(self.head_tree().as_ref(), None, renames)
+    }
+
+    /// Diff from the `from` tree, the empty tree when `None`, to the tree of `to` or to the index when `None`
+    fn tree_diff(&self, from: Option<&Tree<'_>>, to: Option<&str>, renames: bool) -> Result<Diff<'_>> {
+        let mut options = DiffOptions::new();
+        options.minimal(true).include_typechange(true);
+        let mut diff = match to {
+            Some(rev) => self
+                .repo
+                .diff_tree_to_tree(from, Some(&self.tree(rev)?), Some(&mut options))?,
+            None => {
+                let mut index = self.repo.index()?;
+                // the index may have been changed by a `git` command since the repository was opened
+                index.read(false)?;
+                self.repo.diff_tree_to_index(from, Some(&index), Some(&mut options))?
+            }
+        };
+        if renames {
+            diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+        }
+        Ok(diff)
+    }
+
+    fn rev_diff(&self, from: Option<&str>, to: Option<&str>, renames: bool) -> Result<Diff<'_>> {
+        let from = from.map(|rev| self.tree(rev)).transpose()?;
+        self.tree_diff(from.as_ref(), to, renames)
+    }
+
+    /// Whether `git commit` does more than writing the commit: hooks or signing
+    fn needs_git_commit(&self) -> bool {
+        let config = self.repo.config().ok();
+        if config
+            .as_ref()
+            .and_then(|c| c.get_bool("commit.gpgsign").ok())
+            .unwrap_or(false)
+        {
+            return true;
+        }
+        let hooks = match config.as_ref().and_then(|c| c.get_path("core.hooksPath").ok()) {
+            Some(path) => self.root.join(path),
+            None => self.repo.path().join("hooks"),
+        };
+        COMMIT_HOOKS.iter().any(|hook| hooks.join(hook).is_file())
+    }
+}
+
+impl GitBackend for LibGitBackend {
+    fn name(&self) -> &'static str {
+        "libgit2"
+    }
+
+    fn toplevel(&self) -> &Path {
+        &self.root
+    }
+
+    fn staged_files(&self) -> Result<Vec<StagedFile>> {
+        diff_files(&self.index_diff(false)?)
+    }
+
+    fn staged_diff(&self, excludes: &[String]) -> Result<String> {
+        diff_content(&self.index_diff(true)?, excludes)
+    }
+
+    fn changed_files(&self, from: Option<&str>, to: Option<&str>) -> Result<Vec<StagedFile>> {
+        diff_files(&self.rev_diff(from, to, false)?)
+    }
+
+    fn diff(&self, from: Option<&str>, to: Option<&str>, excludes: &[String]) -> Result<String> {
+        diff_content(&self.rev_diff(from, to, true)?, excludes)
+    }
+
+    fn merge_base(&self, one: &str, two: &str) -> Result<String> {
+        let one = self.repo.revparse_single(one)?.peel_to_commit()?.id();
+        let two = self.repo.revparse_single(two)?.peel_to_commit()?.id();
+        Ok(self.repo.merge_base(one, two)?.to_string())
+    }
+
+    fn first_parent(&self, rev: &str) -> Result<Option<String>> {
+        let commit = self.repo.revparse_single(rev)?.peel_to_commit()?;
+        Ok(commit.parent_id(0).ok().map(|id| id.to_string()))
+    }
+
+    fn commit(&self, message: &str) -> Result<()> {
+        if self.needs_git_commit() {
+            return CliBackend::discover(&self.root)?.commit(message);
+        }
+
+        let mut index = self.repo.index()?;
+        index.read(false)?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        let parent = match self.repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(_) => None,
+        };
+        let unchanged = match &parent {
+            Some(parent) => parent.tree_id() == tree.id(),
+            None => tree.is_empty(),
+        };
+        if unchanged {
+            return Err(anyhow!("nothing to commit"));
+        }
+
+        let signature = self.repo.signature()?;
+        let message = git2::message_prettify(message, None)?;
+        let parents: Vec<_> = parent.iter().collect();
+        self.repo
+            .commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents)?;
+        Ok(())
+    }
+
+    fn amend(&self, message: &str) -> Result<()> {
+        if self.needs_git_commit() {
+            return CliBackend::discover(&self.root)?.amend(message);
+        }
+
+        let head = match self.repo.head() {
+            Ok(head) => head.peel_to_commit()?,
+            Err(_) => return Err(anyhow!("no commit to amend")),
+        };
+        let mut index = self.repo.index()?;
+        index.read(false)?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+
+        let signature = self.repo.signature()?;
+        let message = git2::message_prettify(message, None)?;
+        head.amend(Some("HEAD"), None, Some(&signature), None, Some(&message), Some(&tree))?;
+        Ok(())
+    }
+}
+
+/// Changed files of a diff with their line counts
+fn diff_files(diff: &Diff<'_>) -> Result<Vec<StagedFile>> {
+    let mut files = Vec::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(diff, idx)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        let status = match delta.status() {
+            Delta::Added => 'A',
+            Delta::Deleted => 'D',
+            Delta::Typechange => 'T',
+            _ => 'M',
+        };
+        let lines = if delta.flags().is_binary() {
+            None
+        } else {
+            let (_, added, deleted) = patch.line_stats()?;
+            Some((added as u64, deleted as u64))
+        };
+        files.push(StagedFile {
+            status,
+            path: delta_path(&delta),
+            lines,
+        });
+    }
+    Ok(files)
+}
+
+/// Patch text of a diff, leaving out the `excludes` paths on either side of a delta
+fn diff_content(diff: &Diff<'_>, excludes: &[String]) -> Result<String> {
+    let mut content = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let old_path = String::from_utf8_lossy(delta.old_file().path_bytes().unwrap_or_default());
+        if excludes.contains(&delta_path(&delta)) || excludes.iter().any(|e| *e == old_path) {
+            continue;
+        }
+        if let Some(mut patch) = Patch::from_diff(diff, idx)? {
+            content.extend_from_slice(&patch.to_buf()?);
+        }
+    }
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// Path of the delta relative to the root of the working tree, not valid UTF-8 is replaced lossily
+fn delta_path(delta: &git2::DiffDelta<'_>) -> String {
+    let path = delta.new_file().path_bytes().or(delta.old_file().path_bytes());
+    String::from_utf8_lossy(path.unwrap_or_default()).into_owned()
+}
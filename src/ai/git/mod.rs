@@ -0,0 +1,848 @@
+use colored::Colorize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::ai::exclude::{summarize_excluded, Exclusions};
+
+mod backend;
+mod cli;
+mod libgit;
+
+pub use backend::StagedFile;
+
+use backend::GitBackend;
+use cli::CliBackend;
+
+/// Returns whether the current directory is inside a working tree.
+pub fn is_git_directory() -> bool {
+    backend::current().is_ok()
+}
+
+/// Returns the staged paths, listing both sides of renames so that every changed path can be re-staged.
+pub fn git_stage_filenames() -> anyhow::Result<Vec<String>> {
+    Ok(backend::current()?
+        .staged_files()?
+        .into_iter()
+        .map(|f| f.path)
+        .collect())
+}
+
+/// Writes the current index to a tree object and returns its id.
+pub fn git_write_tree() -> anyhow::Result<String> {
+    let output = Command::new("git").arg("write-tree").output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "write tree failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Resets the index to HEAD, the working tree is left untouched.
+pub fn git_reset_index() -> anyhow::Result<()> {
+    let has_head = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .output()?
+        .status
+        .success();
+    let args: &[&str] = if has_head {
+        &["read-tree", "HEAD"]
+    } else {
+        &["read-tree", "--empty"]
+    };
+
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "reset index failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Stages the given paths with their content in `tree`, paths missing from the tree are removed from the index.
+///
+/// # Arguments
+/// * `tree` - The tree object to read the content from, see [`git_write_tree`]
+/// * `paths` - Paths relative to the repository root
+pub fn git_stage_from_tree(tree: &str, paths: &[String]) -> anyhow::Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let root = git_toplevel()?;
+
+    let output = Command::new("git")
+        .current_dir(&root)
+        .args(["ls-tree", "-r", "-z", tree, "--"])
+        .args(paths)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "list tree failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // entries are "<mode> <type> <object>\t<path>", paths absent from the tree are removed with mode 0
+    let mut index_info = String::new();
+    let mut found = Vec::new();
+    for entry in String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|s| !s.is_empty())
+    {
+        if let Some((meta, path)) = entry.split_once('\t') {
+            let mut fields = meta.split(' ');
+            let (mode, object) = (fields.next().unwrap_or_default(), fields.nth(1).unwrap_or_default());
+            index_info.push_str(&format!("{mode} {object}\t{path}\n"));
+            found.push(path.to_string());
+        }
+    }
+    for path in paths.iter().filter(|p| !found.contains(p)) {
+        index_info.push_str(&format!("0 {}\t{path}\n", "0".repeat(40)));
+    }
+
+    let mut child = Command::new("git")
+        .current_dir(&root)
+        .args(["update-index", "--index-info"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(index_info.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "update index failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the root directory of the working tree.
+pub fn git_toplevel() -> anyhow::Result<PathBuf> {
+    Ok(backend::current()?.toplevel().to_path_buf())
+}
+
+/// Returns the git directory of the working tree, e.g. `.git`, or the directory of the worktree in `.git/worktrees`.
+pub fn git_dir() -> anyhow::Result<PathBuf> {
+    Ok(PathBuf::from(git_output(&["rev-parse", "--absolute-git-dir"], &[])?))
+}
+
+/// Returns the diff content of staged files in the git repository.
+/// The files matching the `ignore` patterns or `.gitbuddyignore`, both in gitignore syntax,
+/// are left out and summarised in one line each in front of the diff.
+pub fn git_stage_diff(ignore: &[String]) -> anyhow::Result<String> {
+    let backend = backend::current()?;
+    let excluded = excluded_files(backend.toplevel(), backend.staged_files()?, ignore);
+    let excluded_paths: Vec<String> = excluded.iter().map(|f| f.path.clone()).collect();
+
+    Ok(format!(
+        "{}{}",
+        summarize_excluded(&excluded),
+        backend.staged_diff(&excluded_paths)?
+    ))
+}
+
+/// Returns the diff between `from` and HEAD, with the same exclusions as [`git_stage_diff`].
+pub fn git_range_diff(from: &str, ignore: &[String]) -> anyhow::Result<String> {
+    Ok(revs_changes(backend::current()?.as_ref(), Some(from), Some("HEAD"), ignore)?.1)
+}
+
+/// Returns the changed files and the diff replaced by `git commit --amend`: the HEAD commit and the staged changes.
+pub fn git_amend_changes(ignore: &[String]) -> anyhow::Result<(Vec<String>, String)> {
+    amend_changes(backend::current()?.as_ref(), ignore)
+}
+
+/// Returns the changed files and the diff of a commit.
+pub fn git_commit_changes(rev: &str, ignore: &[String]) -> anyhow::Result<(Vec<String>, String)> {
+    commit_changes(backend::current()?.as_ref(), rev, ignore)
+}
+
+fn amend_changes(backend: &dyn GitBackend, ignore: &[String]) -> anyhow::Result<(Vec<String>, String)> {
+    let parent = backend.first_parent("HEAD")?;
+    revs_changes(backend, parent.as_deref(), None, ignore)
+}
+
+fn commit_changes(backend: &dyn GitBackend, rev: &str, ignore: &[String]) -> anyhow::Result<(Vec<String>, String)> {
+    let parent = backend.first_parent(rev)?;
+    revs_changes(backend, parent.as_deref(), Some(rev), ignore)
+}
+
+/// Returns the changed files and the combined diff of the commits of a range `<from>..<to>`, compared to the
+/// merge base of both ends as `git diff <from>...<to>`.
+pub fn git_squash_changes(from: &str, to: &str, ignore: &[String]) -> anyhow::Result<(Vec<String>, String)> {
+    let backend = backend::current()?;
+    let merge_base = backend.merge_base(from, to)?;
+    revs_changes(backend.as_ref(), Some(&merge_base), Some(to), ignore)
+}
+
+/// Changed files and diff from `from` to `to`, see [`GitBackend::changed_files`], with the same exclusions as
+/// [`git_stage_diff`]
+fn revs_changes(
+    backend: &dyn GitBackend,
+    from: Option<&str>,
+    to: Option<&str>,
+    ignore: &[String],
+) -> anyhow::Result<(Vec<String>, String)> {
+    let files = backend.changed_files(from, to)?;
+    let filenames = files.iter().map(|f| f.path.clone()).collect();
+    let excluded = excluded_files(backend.toplevel(), files, ignore);
+    let excluded_paths: Vec<String> = excluded.iter().map(|f| f.path.clone()).collect();
+
+    let diff = format!(
+        "{}{}",
+        summarize_excluded(&excluded),
+        backend.diff(from, to, &excluded_paths)?
+    );
+    Ok((filenames, diff))
+}
+
+fn excluded_files(root: &Path, files: Vec<StagedFile>, ignore: &[String]) -> Vec<StagedFile> {
+    let exclusions = Exclusions::new(root, ignore);
+    files.into_iter().filter(|f| exclusions.is_excluded(&f.path)).collect()
+}
+
+/// Returns the base branch of a pull request of the current branch.
+///
+/// The upstream of the branch is used unless it is the branch itself on the remote, then the default
+/// branch of `origin`.
+pub fn git_pr_base() -> anyhow::Result<String> {
+    let rev_parse = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    let branch = rev_parse(&["rev-parse", "--abbrev-ref", "HEAD"]).unwrap_or_default();
+    if let Some(upstream) = rev_parse(&["rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{upstream}"]) {
+        // the upstream of a pushed feature branch is the feature branch itself
+        let upstream_branch = upstream.split_once('/').map_or(upstream.as_str(), |(_, b)| b);
+        if upstream_branch != branch {
+            return Ok(upstream);
+        }
+    }
+    rev_parse(&["symbolic-ref", "--short", "refs/remotes/origin/HEAD"])
+        .ok_or_else(|| anyhow::anyhow!("cannot detect the base branch of {}, use --base", branch))
+}
+
+/// Returns the best common ancestor of `base` and HEAD.
+pub fn git_merge_base(base: &str) -> anyhow::Result<String> {
+    backend::current()?
+        .merge_base(base, "HEAD")
+        .map_err(|e| anyhow::anyhow!("no common ancestor of {} and HEAD: {}", base, e))
+}
+
+/// Returns the messages of the non-merge commits of a revision range, oldest first.
+pub fn git_range_messages(range: &str) -> anyhow::Result<Vec<String>> {
+    Ok(git_range_commits(range)?
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect())
+}
+
+/// Returns the abbreviated hashes and messages of the non-merge commits of a revision range, oldest first.
+pub fn git_range_commits(range: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let output = Command::new("git")
+        .args(["log", "--no-merges", "--reverse", "--format=%h%x1f%B%x00", range])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "log {} failed: {}",
+            range,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter_map(|entry| entry.trim_start().split_once('\x1f'))
+        .map(|(hash, message)| (hash.to_string(), message.trim().to_string()))
+        .collect())
+}
+
+/// Returns the tag pointing at `rev` and the date of its commit as `YYYY-MM-DD`, `None` when it is not tagged.
+pub fn git_tag_of(rev: &str) -> Option<(String, String)> {
+    let run = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+    };
+    let tag = run(&["describe", "--tags", "--exact-match", rev])?;
+    let date = run(&["log", "-1", "--format=%cs", rev])?;
+    Some((tag, date))
+}
+
+/// Returns the messages of the most recent non-merge commits, newest first.
+///
+/// # Arguments
+/// * `number` - The maximum number of commits
+/// * `paths` - Only commits touching these paths relative to the root of the working tree, all commits when empty
+pub fn git_log_messages(number: usize, paths: &[String]) -> Vec<String> {
+    let mut command = Command::new("git");
+    command.args(["log", "--no-merges", &format!("-n{}", number), "--format=%B%x00"]);
+    if !paths.is_empty() {
+        // the staged paths are relative to the root, not to the current directory
+        command
+            .arg("--")
+            .args(paths.iter().map(|path| format!(":(top,literal){}", path)));
+    }
+
+    match command.output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Commits the changes to the repository.
+///
+/// # Arguments
+/// * `message` - The commit message to use
+/// * `dry_run` - If true, only simulate the commit without actually performing it
+///
+/// # Returns
+/// * `Ok(())` if the commit was successful or it was a dry run
+/// * `Err` if the commit failed
+pub fn git_commit(message: &str, dry_run: bool) -> anyhow::Result<()> {
+    commit_with(message, false, dry_run)
+}
+
+/// Replaces the HEAD commit by a commit of the index with the new message, as `git commit --amend`.
+///
+/// # Arguments
+/// * `message` - The commit message to use
+/// * `dry_run` - If true, only simulate the commit without actually performing it
+pub fn git_amend(message: &str, dry_run: bool) -> anyhow::Result<()> {
+    commit_with(message, true, dry_run)
+}
+
+fn commit_with(message: &str, amend: bool, dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        return Ok(());
+    }
+
+    eprintln!(
+        "\n{} {} {}",
+        "🚀".bright_yellow(),
+        "Executing Git Commit".bright_cyan().bold(),
+        "...".bright_yellow()
+    );
+
+    let result = backend::current().and_then(|backend| {
+        let result = if amend {
+            backend.amend(message)
+        } else {
+            backend.commit(message)
+        };
+        result.map_err(|e| anyhow::anyhow!("{} ({})", e, backend.name()))
+    });
+
+    if result.is_ok() {
+        eprintln!(
+            "{} {} {}\n",
+            "✨".bright_green(),
+            "Commit Successfully".bright_green().bold(),
+            "🎉".bright_green()
+        );
+        Ok(())
+    } else {
+        eprintln!(
+            "{} {} {}\n",
+            "❌".bright_red(),
+            "Commit Failed".bright_red().bold(),
+            "💔".bright_red()
+        );
+        result.map_err(|e| anyhow::anyhow!("commit failed: {}", e))
+    }
+}
+
+/// Fails when any of the commits is already on the upstream branch, published history is never rewritten.
+pub fn git_check_unpublished(commits: &[String]) -> anyhow::Result<()> {
+    CliBackend::discover(&std::env::current_dir()?)?.check_unpublished(commits)
+}
+
+/// Rewrites the messages of commits of the current branch in a single history rewrite, leaving every tree untouched.
+///
+/// The commits are recreated with their new message, keeping their author, and so are the commits on top of them.
+/// HEAD is moved last, the index and the working tree do not change.
+///
+/// # Arguments
+/// * `messages` - The commits to reword, ancestors of HEAD, and their new messages
+/// * `dry_run` - If true, only check the commits without rewriting them
+pub fn git_rewrite_messages(messages: &[(String, String)], dry_run: bool) -> anyhow::Result<()> {
+    CliBackend::discover(&std::env::current_dir()?)?.rewrite_messages(messages, dry_run)
+}
+
+/// Returns a value of the git configuration, `None` when it is not set.
+pub fn git_config(key: &str) -> Option<String> {
+    git_output(&["config", "--get", key], &[])
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+/// Returns the prefix of comment lines in commit messages, `core.commentChar` or `#`.
+///
+/// With `auto` git picks a character the message does not use, `#` is assumed, as for the messages of GitBuddy.
+pub fn git_comment_prefix() -> String {
+    git_config("core.commentChar")
+        .filter(|c| c != "auto")
+        .unwrap_or_else(|| "#".to_string())
+}
+
+/// Returns the name of the checked out branch, `None` on a detached HEAD.
+pub fn git_current_branch() -> Option<String> {
+    git_output(&["symbolic-ref", "--short", "-q", "HEAD"], &[])
+        .ok()
+        .filter(|branch| !branch.is_empty())
+}
+
+/// Adds trailers to a message as their own final block, formatted by `git interpret-trailers`.
+///
+/// They are never merged into a footer the model wrote, and the trailers the message already has are left out.
+pub fn git_interpret_trailers(message: &str, trailers: &[String]) -> anyhow::Result<String> {
+    let message = message.trim_end();
+    let trailers: Vec<&String> = trailers
+        .iter()
+        .filter(|trailer| !message.lines().any(|line| line.trim() == trailer.trim()))
+        .collect();
+    if trailers.is_empty() {
+        return Ok(message.to_string());
+    }
+    let mut command = Command::new("git");
+    command.args(["interpret-trailers", "--if-exists", "addIfDifferent"]);
+    for trailer in trailers {
+        command.args(["--trailer", trailer]);
+    }
+    // an empty input only gets the trailers, which keeps them out of the message
+    let output = command.stdin(Stdio::null()).output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "interpret-trailers failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(format!(
+        "{}\n\n{}",
+        message,
+        String::from_utf8_lossy(&output.stdout).trim()
+    ))
+}
+
+/// Runs a git command, returning its trimmed output
+fn git_output(args: &[&str], envs: &[(&str, &str)]) -> anyhow::Result<String> {
+    let output = Command::new("git").args(args).envs(envs.iter().copied()).output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Pushes the changes to the remote repository.
+///
+/// # Arguments
+/// * `dry_run` - If true, only simulate the push without actually performing it
+///
+/// # Returns
+/// * `Ok(())` if the push was successful or it was a dry run
+/// * `Err` if the push failed
+pub fn git_push(dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        return Ok(());
+    }
+
+    let output = Command::new("git").args(["push", "origin", "HEAD"]).output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("push failed"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::backend::GitBackend;
+    use super::cli::CliBackend;
+    use super::libgit::LibGitBackend;
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git").current_dir(dir).args(args).output().unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// A repository with one commit of `a.txt` and `yarn.lock`
+    fn temp_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["config", "user.name", "GitBuddy"]);
+        git(dir.path(), &["config", "user.email", "gitbuddy@example.com"]);
+        git(dir.path(), &["config", "commit.gpgsign", "false"]);
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        fs::write(dir.path().join("yarn.lock"), "1\n").unwrap();
+        git(dir.path(), &["add", "-A"]);
+        git(dir.path(), &["commit", "-q", "-m", "init"]);
+        dir
+    }
+
+    fn backends(dir: &Path) -> Vec<Box<dyn GitBackend>> {
+        vec![
+            Box::new(LibGitBackend::discover(dir).unwrap()),
+            Box::new(CliBackend::discover(dir).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_discover() {
+        let repo = temp_repo();
+        let sub = repo.path().join("src");
+        fs::create_dir(&sub).unwrap();
+        for backend in backends(&sub) {
+            assert_eq!(
+                backend.toplevel().canonicalize().unwrap(),
+                repo.path().canonicalize().unwrap(),
+                "{}",
+                backend.name()
+            );
+        }
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(LibGitBackend::discover(outside.path()).is_err());
+        assert!(CliBackend::discover(outside.path()).is_err());
+        assert!(backend::open(outside.path()).is_err());
+    }
+
+    #[test]
+    fn test_staged_files() {
+        let repo = temp_repo();
+        fs::write(repo.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        fs::write(repo.path().join("logo.png"), b"\x89PNG\0\x01").unwrap();
+        fs::remove_file(repo.path().join("yarn.lock")).unwrap();
+        fs::write(repo.path().join("unstaged.txt"), "x\n").unwrap();
+        git(repo.path(), &["add", "a.txt", "logo.png", "yarn.lock"]);
+
+        let expected = vec![
+            StagedFile {
+                status: 'M',
+                path: "a.txt".to_string(),
+                lines: Some((2, 0)),
+            },
+            StagedFile {
+                status: 'A',
+                path: "logo.png".to_string(),
+                lines: None,
+            },
+            StagedFile {
+                status: 'D',
+                path: "yarn.lock".to_string(),
+                lines: Some((0, 1)),
+            },
+        ];
+        for backend in backends(repo.path()) {
+            assert_eq!(backend.staged_files().unwrap(), expected, "{}", backend.name());
+        }
+    }
+
+    #[test]
+    fn test_staged_diff_excludes_non_utf8() {
+        let repo = temp_repo();
+        fs::write(repo.path().join("a.txt"), b"one\ncaf\xe9\n").unwrap();
+        fs::write(repo.path().join("yarn.lock"), "2\n").unwrap();
+        git(repo.path(), &["add", "-A"]);
+
+        for backend in backends(repo.path()) {
+            let diff = backend.staged_diff(&["yarn.lock".to_string()]).unwrap();
+            assert!(
+                diff.starts_with("diff --git a/a.txt b/a.txt\n"),
+                "{}: {}",
+                backend.name(),
+                diff
+            );
+            assert!(diff.contains("+caf\u{FFFD}\n"), "{}: {}", backend.name(), diff);
+            assert!(!diff.contains("yarn.lock"), "{}: {}", backend.name(), diff);
+
+            let diff = backend.staged_diff(&[]).unwrap();
+            assert!(
+                diff.contains("diff --git a/yarn.lock b/yarn.lock\n"),
+                "{}",
+                backend.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_range_diff() {
+        let repo = temp_repo();
+        let base = git(repo.path(), &["rev-parse", "HEAD"]).trim().to_string();
+        fs::write(repo.path().join("a.txt"), "one\ntwo\n").unwrap();
+        fs::write(repo.path().join("yarn.lock"), "2\n").unwrap();
+        git(repo.path(), &["commit", "-q", "-a", "-m", "feat: two"]);
+        git(repo.path(), &["checkout", "-q", "-b", "side", &base]);
+        fs::write(repo.path().join("b.txt"), "b\n").unwrap();
+        git(repo.path(), &["add", "b.txt"]);
+        git(repo.path(), &["commit", "-q", "-m", "feat: b"]);
+
+        for backend in backends(repo.path()) {
+            let name = backend.name();
+            let files = backend.changed_files(Some(&base), Some("@{-1}")).unwrap();
+            assert_eq!(
+                files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+                ["a.txt", "yarn.lock"],
+                "{name}"
+            );
+            assert_eq!(files[0].lines, Some((1, 0)), "{name}");
+
+            let diff = backend
+                .diff(Some(&base), Some("@{-1}"), &["yarn.lock".to_string()])
+                .unwrap();
+            assert!(diff.contains("+two\n"), "{name}: {diff}");
+            assert!(!diff.contains("yarn.lock"), "{name}: {diff}");
+
+            assert_eq!(backend.merge_base("HEAD", "@{-1}").unwrap(), base, "{name}");
+            assert_eq!(
+                backend.first_parent("HEAD").unwrap().as_deref(),
+                Some(base.as_str()),
+                "{name}"
+            );
+            assert_eq!(backend.first_parent(&base).unwrap(), None, "{name}");
+            assert!(backend.first_parent("missing").is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_amend_changes() {
+        let repo = temp_repo();
+        fs::write(repo.path().join("a.txt"), "one\ntwo\n").unwrap();
+        git(repo.path(), &["commit", "-q", "-a", "-m", "wip"]);
+        fs::write(repo.path().join("b.txt"), "b\n").unwrap();
+        fs::write(repo.path().join("unstaged.txt"), "x\n").unwrap();
+        git(repo.path(), &["add", "b.txt"]);
+
+        for backend in backends(repo.path()) {
+            let name = backend.name();
+            // the changes of HEAD and the staged changes, not the unstaged ones
+            let (files, diff) = amend_changes(backend.as_ref(), &[]).unwrap();
+            assert_eq!(files, ["a.txt", "b.txt"], "{name}");
+            assert!(diff.contains("+two\n") && diff.contains("+b\n"), "{name}: {diff}");
+            assert!(!diff.contains("unstaged.txt"), "{name}: {diff}");
+        }
+    }
+
+    #[test]
+    fn test_root_commit_changes() {
+        let repo = temp_repo();
+        for backend in backends(repo.path()) {
+            let name = backend.name();
+            // the root commit is compared to the empty tree, lock files are only summarised
+            let (files, diff) = commit_changes(backend.as_ref(), "HEAD", &["yarn.lock".to_string()]).unwrap();
+            assert_eq!(files, ["a.txt", "yarn.lock"], "{name}");
+            assert!(
+                diff.contains("new file mode 100644") && diff.contains("+one\n"),
+                "{name}: {diff}"
+            );
+            assert!(!diff.contains("diff --git a/yarn.lock"), "{name}: {diff}");
+
+            let (files, _) = amend_changes(backend.as_ref(), &[]).unwrap();
+            assert_eq!(files, ["a.txt", "yarn.lock"], "{name}");
+        }
+    }
+
+    #[test]
+    fn test_reword_changes() {
+        let repo = branch_repo();
+        fs::write(repo.path().join("a.txt"), "staged\n").unwrap();
+        git(repo.path(), &["add", "a.txt"]);
+        for backend in backends(repo.path()) {
+            let name = backend.name();
+            // a commit below HEAD is diffed against its own parent, HEAD and the index do not matter
+            let (files, diff) = commit_changes(backend.as_ref(), "HEAD~2", &[]).unwrap();
+            assert_eq!(files, ["b.txt"], "{name}");
+            assert!(diff.starts_with("diff --git a/b.txt b/b.txt\n"), "{name}: {diff}");
+            assert!(diff.contains("+wip"), "{name}: {diff}");
+            assert!(commit_changes(backend.as_ref(), "missing", &[]).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_commit() {
+        for backend_name in ["libgit2", "git"] {
+            let repo = temp_repo();
+            let backend: Box<dyn GitBackend> = match backend_name {
+                "libgit2" => Box::new(LibGitBackend::discover(repo.path()).unwrap()),
+                _ => Box::new(CliBackend::discover(repo.path()).unwrap()),
+            };
+            assert!(backend.commit("chore: nothing").is_err(), "{}", backend_name);
+
+            fs::write(repo.path().join("a.txt"), "two\n").unwrap();
+            git(repo.path(), &["add", "a.txt"]);
+            backend.commit("fix(a): replace one with two\n\nbody").unwrap();
+
+            assert_eq!(
+                git(repo.path(), &["log", "-1", "--format=%s%n%b"]).trim(),
+                "fix(a): replace one with two\nbody",
+                "{}",
+                backend_name
+            );
+            assert_eq!(git(repo.path(), &["status", "--porcelain"]), "", "{}", backend_name);
+            assert_eq!(git(repo.path(), &["rev-list", "--count", "HEAD"]).trim(), "2");
+
+            fs::write(repo.path().join("b.txt"), "b\n").unwrap();
+            git(repo.path(), &["add", "b.txt"]);
+            backend.amend("feat: add b").unwrap();
+            assert_eq!(
+                git(repo.path(), &["log", "-1", "--format=%s"]).trim(),
+                "feat: add b",
+                "{}",
+                backend_name
+            );
+            assert_eq!(git(repo.path(), &["rev-list", "--count", "HEAD"]).trim(), "2");
+            assert_eq!(
+                git(repo.path(), &["show", "--format=", "--name-only", "HEAD"]),
+                "a.txt\nb.txt\n"
+            );
+        }
+    }
+
+    /// `temp_repo` with four more commits, the second one by another author at a fixed date, the third one with a
+    /// message kept verbatim
+    fn branch_repo() -> tempfile::TempDir {
+        let repo = temp_repo();
+        let commits: [(&str, &str, &[&str]); 4] = [
+            ("a.txt", "feat: two", &[]),
+            (
+                "b.txt",
+                "wip",
+                &["--author=Ana <ana@example.com>", "--date=1700000000 +0200"],
+            ),
+            (
+                "c.txt",
+                "fix: three\n\n  indented body  \n\n\n",
+                &["--cleanup=verbatim"],
+            ),
+            ("d.txt", "wip again", &[]),
+        ];
+        for (file, message, options) in commits {
+            fs::write(repo.path().join(file), message).unwrap();
+            git(repo.path(), &["add", file]);
+            let mut args = vec!["commit", "-q", "-m", message];
+            args.extend(options);
+            git(repo.path(), &args);
+        }
+        repo
+    }
+
+    /// Tree, author, date and raw message of each commit of HEAD, newest first
+    fn history(dir: &Path) -> Vec<(String, String, String)> {
+        git(dir, &["rev-list", "HEAD"])
+            .lines()
+            .map(|commit| {
+                let meta = git(dir, &["log", "-1", "--format=%T %an <%ae> %ad", "--date=raw", commit]);
+                let object = git(dir, &["cat-file", "commit", commit]);
+                let message = object.split_once("\n\n").unwrap().1.to_string();
+                (commit.to_string(), meta.trim_end().to_string(), message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rewrite_messages() {
+        let repo = branch_repo();
+        let before = history(repo.path());
+        let backend = CliBackend::discover(repo.path()).unwrap();
+
+        let messages = [
+            ("HEAD~2".to_string(), "feat: add b".to_string()),
+            ("HEAD".to_string(), "feat: add d\n\nbody".to_string()),
+        ];
+        backend.rewrite_messages(&messages, true).unwrap();
+        assert_eq!(history(repo.path()), before);
+
+        backend.rewrite_messages(&messages, false).unwrap();
+        let after = history(repo.path());
+        assert_eq!(after.len(), before.len());
+        // trees, authors and author dates are kept
+        for ((_, meta_after, _), (_, meta_before, _)) in after.iter().zip(&before) {
+            assert_eq!(meta_after, meta_before);
+        }
+        assert!(before[2].1.ends_with("Ana <ana@example.com> 1700000000 +0200"));
+        assert_eq!(after[0].2, "feat: add d\n\nbody\n");
+        assert_eq!(after[2].2, "feat: add b\n");
+        // the commits in between keep their messages byte for byte, the ones below are not recreated
+        assert_eq!(after[1].2, "fix: three\n\n  indented body  \n\n\n");
+        assert_ne!(after[1].0, before[1].0);
+        assert_eq!(after[3], before[3]);
+        assert_eq!(after[4], before[4]);
+        assert_eq!(git(repo.path(), &["status", "--porcelain"]), "");
+
+        let head = git(repo.path(), &["rev-parse", "HEAD"]);
+        git(repo.path(), &["checkout", "-q", "-b", "other", "HEAD~1"]);
+        git(repo.path(), &["commit", "-q", "--allow-empty", "-m", "other"]);
+        git(repo.path(), &["checkout", "-q", "-"]);
+        let error = backend
+            .rewrite_messages(&[("other".to_string(), "x".to_string())], false)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "other is not a commit of the current branch");
+        assert_eq!(git(repo.path(), &["rev-parse", "HEAD"]), head);
+    }
+
+    #[test]
+    fn test_check_unpublished() {
+        let repo = branch_repo();
+        let backend = CliBackend::discover(repo.path()).unwrap();
+        let commits = ["HEAD~3".to_string(), "HEAD~1".to_string(), "HEAD".to_string()];
+        // without an upstream everything may be rewritten
+        backend.check_unpublished(&commits).unwrap();
+
+        git(repo.path(), &["branch", "base", "HEAD~2"]);
+        git(repo.path(), &["branch", "--set-upstream-to=base"]);
+        let error = backend.check_unpublished(&commits).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "already on the upstream branch, refusing to rewrite: HEAD~3"
+        );
+        backend.check_unpublished(&commits[1..]).unwrap();
+    }
+
+    #[test]
+    fn test_interpret_trailers() {
+        let trailers = ["Refs: PROJ-123".to_string(), "Signed-off-by: A <a@b.c>".to_string()];
+        assert_eq!(
+            git_interpret_trailers("feat: add b\n\nThe body.", &trailers).unwrap(),
+            "feat: add b\n\nThe body.\n\nRefs: PROJ-123\nSigned-off-by: A <a@b.c>"
+        );
+        assert_eq!(
+            git_interpret_trailers("fix: crash\n\nRefs: PROJ-123", &trailers).unwrap(),
+            "fix: crash\n\nRefs: PROJ-123\n\nSigned-off-by: A <a@b.c>"
+        );
+        // the footer the model wrote stays apart from ours
+        assert_eq!(
+            git_interpret_trailers("fix: crash\n\nCloses: #7", &trailers).unwrap(),
+            "fix: crash\n\nCloses: #7\n\nRefs: PROJ-123\nSigned-off-by: A <a@b.c>"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use colored::Colorize;
+use std::io::Read;
+use std::path::Path;
+
+use crate::ai::git;
+use crate::conventional::lint::{self, LintRules, Severity};
+
+/// Lints commit messages: of a revision range, of a file such as `.git/COMMIT_EDITMSG`, or read from stdin.
+///
+/// Fails when any message breaks a rule with error severity, warnings are only reported.
+pub fn handler(file: Option<&Path>, range: Option<&str>, rules: &LintRules) -> anyhow::Result<()> {
+    let messages: Vec<(String, String)> = match (range, file) {
+        (Some(range), _) => git::git_range_commits(range)?,
+        (None, Some(path)) if path != Path::new("-") => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?;
+            vec![(path.display().to_string(), lint::strip_comments(&content, &git::git_comment_prefix()))]
+        }
+        (None, _) => {
+            let mut content = String::new();
+            std::io::stdin().read_to_string(&mut content)?;
+            vec![("stdin".to_string(), lint::strip_comments(&content, &git::git_comment_prefix()))]
+        }
+    };
+
+    let mut failed = 0;
+    for (name, message) in &messages {
+        let violations = lint::lint(message, rules);
+        if violations.is_empty() {
+            continue;
+        }
+        let header = message.lines().next().unwrap_or_default();
+        println!("{} {}", name.bright_yellow(), header.bright_cyan());
+        for violation in &violations {
+            println!("  {}", violation);
+        }
+        if violations.iter().any(|v| v.severity == Severity::Error) {
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} commit messages failed", failed, messages.len()));
+    }
+    println!("{} {} commit messages checked", "✔".green(), messages.len());
+    Ok(())
+}
@@ -1,13 +1,28 @@
 use colored::Colorize;
 use std::time::Instant;
 
-use crate::ai::git::{git_stage_diff, git_stage_filenames};
+use crate::ai::git::{git_stage_diff, git_stage_filenames, is_git_directory};
+use crate::conventional::lint::LintRules;
+use crate::ai::picker::Picked;
 use crate::llm::Confirm;
 use crate::prompt::Prompt;
+use crate::args::Output;
 use crate::{args, llm};
 
-mod git;
+pub mod changelog;
+mod editor;
+mod exclude;
+pub(crate) mod git;
+mod output;
+mod picker;
+pub mod lint;
+pub mod reword;
+pub mod rewrite;
+pub mod squash;
+pub mod pr;
+mod split;
 mod theme;
+pub mod trailers;
 
 fn get_stats_separator() -> String {
     format!(
@@ -39,51 +54,60 @@ fn format_stat(label: &str, value: i64, emoji: &str) -> Option<String> {
     ))
 }
 
-pub fn handler(prompt: Prompt, args: args::CommandArgs) -> Result<(), Box<dyn std::error::Error>> {
+pub fn handler(prompt: Prompt, args: args::CommandArgs) -> anyhow::Result<()> {
     if !is_git_directory() {
-        println!("Not git directory");
+        eprintln!("Not git directory");
         return Ok(());
     }
 
-    if !is_git_installed() {
-        println!("Please install git");
-        return Ok(());
+    if args.amend {
+        return amend(&args);
     }
 
-    let filenames = git_stage_filenames();
+    let filenames = git_stage_filenames()?;
     if filenames.is_empty() {
-        println!("No files added to staging! Did you forget to run `git add` ?");
+        eprintln!("No files added to staging! Did you forget to run `git add` ?");
         return Ok(());
     }
 
-    let diff_content = git_stage_diff();
+    let diff_content = git_stage_diff(&args.ignore)?;
     // let diff_content = format!("Code changes: \n```\n{}\n```", git_stage_diff());
 
-    println!("{}", get_command_message());
-    let start = Instant::now();
-    let llm_result = llm::llm_request(&diff_content, prompt, &args)?;
-    theme::print_stats(&llm_result, start.elapsed());
-
-    let confirm = llm::confirm_commit(&llm_result)?;
-    let commit_message = match confirm {
-        Confirm::Retry | Confirm::Exit => {
-            println!("{}", "Cancel commit".red());
-            None
+    if args.output == Output::Json && args.split {
+        return Err(anyhow::anyhow!("--output json does not support --split"));
+    }
+    if args.output == Output::Text {
+        println!("{}", get_command_message());
+    }
+    if args.split {
+        let style = commit_style(&args, &filenames);
+        return split::handler(&diff_content, &style, &args);
+    }
+
+    let style = commit_style(&args, &filenames);
+    let mut staging = picker::Staging::new(filenames.clone())?;
+    let commit_message = match generate_message(&diff_content, &filenames, prompt, &style, Some(&mut staging), &args) {
+        Ok(Some(message)) => message,
+        result => {
+            // nothing is committed, files left out in the picker are staged again
+            staging.restore()?;
+            result?;
+            return Ok(());
         }
-        Confirm::Ok(msg) => Some(msg),
     };
 
-    let result = git::git_commit(commit_message.unwrap(), args.dry_run);
-    match result {
-        Ok(_) => {}
-        Err(e) => {
-            eprintln!("{}", e)
-        }
+    let result = git::git_interpret_trailers(&commit_message, &args.trailers)
+        .and_then(|message| git::git_commit(&message, args.dry_run));
+    if let Err(e) = result {
+        eprintln!("{}", e)
     }
+    // files left out in the picker stay staged for the next commit
+    staging.restore()?;
 
     // push
     if args.push {
         match git::git_push(args.dry_run) {
+            Ok(_) if args.output == Output::Json => {}
             Ok(_) => {
                 println!("{}", "Push success!!!".green())
             }
@@ -96,10 +120,138 @@ pub fn handler(prompt: Prompt, args: args::CommandArgs) -> Result<(), Box<dyn st
     Ok(())
 }
 
-fn is_git_directory() -> bool {
-    return std::process::Command::new("git").arg("rev-parse").output().is_ok();
+/// Regenerates the message of the HEAD commit from its changes and the staged changes, and amends it
+fn amend(args: &args::CommandArgs) -> anyhow::Result<()> {
+    let (filenames, diff_content) = git::git_amend_changes(&args.ignore)?;
+    if filenames.is_empty() {
+        eprintln!("Nothing to amend");
+        return Ok(());
+    }
+
+    if args.output == Output::Text {
+        println!("{}", get_command_message());
+    }
+    let style = commit_style(args, &filenames);
+    let Some(commit_message) = generate_message(&diff_content, &filenames, args.prompt, &style, None, args)? else {
+        return Ok(());
+    };
+    git::git_amend(&git::git_interpret_trailers(&commit_message, &args.trailers)?, args.dry_run)
+}
+
+/// Generates commit messages for the diff and lets the user pick one, regenerating on request.
+/// With `--output json` the messages are printed as JSON instead, and only the option of `--yes` is returned.
+///
+/// Returns `None` when the user cancels.
+pub fn generate_message(
+    diff_content: &str,
+    filenames: &[String],
+    prompt: Prompt,
+    style: &llm::CommitStyle,
+    staging: Option<&mut picker::Staging>,
+    args: &args::CommandArgs,
+) -> anyhow::Result<Option<String>> {
+    let start = Instant::now();
+    let llm_result = llm::llm_request(diff_content, prompt, style, args)?;
+    if args.output == Output::Json {
+        output::print_json(&llm_result, start.elapsed(), args)?;
+        if args.yes.is_none() {
+            return Ok(None);
+        }
+    } else {
+        theme::print_stats(&llm_result.usage(), start.elapsed());
+    }
+    choose_message(llm_result, diff_content, filenames, staging, args)
+}
+
+/// Lets the user pick one of the generated commit messages, regenerating on request, and opens it in the editor
+/// when asked to. The full-screen picker is used on a terminal, it shows `diff_content` and lets staged files be
+/// left out when `staging` is given. The editor lists `filenames`, the files of the commit.
+///
+/// The option of `--yes` is returned without asking.
+///
+/// Returns `None` when the user cancels or empties the message in the editor.
+pub fn choose_message(
+    mut llm_result: llm::LLMResult,
+    diff_content: &str,
+    filenames: &[String],
+    mut staging: Option<&mut picker::Staging>,
+    args: &args::CommandArgs,
+) -> anyhow::Result<Option<String>> {
+    if let Some(option) = args.yes {
+        let message = (option as usize).checked_sub(1).and_then(|idx| llm_result.commit_messages.get(idx));
+        return match message {
+            Some(message) => Ok(Some(message.clone())),
+            None => Err(anyhow::anyhow!(
+                "--yes {}: only {} options were generated",
+                option,
+                llm_result.commit_messages.len()
+            )),
+        };
+    }
+
+    let lint_rules = LintRules::new(args.scopes.clone());
+    let mut diff_content = diff_content.to_string();
+    let mut filenames = filenames.to_vec();
+    loop {
+        let picked = if picker::is_available() {
+            picker::pick(&llm_result.commit_messages, &lint_rules, &diff_content, staging.as_deref_mut())?
+        } else {
+            match llm::confirm_commit(&llm_result, &lint_rules) {
+                Confirm::Ok(msg) => Picked::Commit(msg.clone()),
+                Confirm::Edit(msg) => Picked::Edit(msg.clone()),
+                Confirm::Retry(feedback) => Picked::Retry(feedback),
+                Confirm::Exit => Picked::Exit,
+            }
+        };
+        match picked {
+            Picked::Commit(msg) if !args.edit => return Ok(Some(msg)),
+            Picked::Commit(msg) | Picked::Edit(msg) => {
+                let edited = editor::edit_message(&msg, &filenames)?;
+                if edited.is_none() {
+                    println!("{}", "Aborting commit due to empty commit message".red());
+                }
+                return Ok(edited);
+            }
+            Picked::Retry(feedback) => {
+                let start = Instant::now();
+                llm_result = llm::llm_retry(&llm_result, feedback.as_deref(), args)?;
+                theme::print_stats(&llm_result.usage(), start.elapsed());
+            }
+            Picked::Restaged => {
+                filenames = git_stage_filenames()?;
+                diff_content = git_stage_diff(&args.ignore)?;
+                let start = Instant::now();
+                llm_result = llm::llm_request(&diff_content, args.prompt, &commit_style(args, &filenames), args)?;
+                theme::print_stats(&llm_result.usage(), start.elapsed());
+            }
+            Picked::Exit => {
+                println!("{}", "Cancel commit".red());
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Fails with `--output json`, for the commands printing decorated text only
+fn require_text_output(command: &str, args: &args::CommandArgs) -> anyhow::Result<()> {
+    if args.output == Output::Json {
+        return Err(anyhow::anyhow!("--output json does not support {}", command));
+    }
+    Ok(())
 }
 
-fn is_git_installed() -> bool {
-    return std::process::Command::new("git").arg("--version").output().is_ok();
+/// Learns the commit style from the repository history, as configured by `--history` and `--history-paths`.
+///
+/// Configured scopes replace the scopes learned from the history.
+pub fn commit_style(args: &args::CommandArgs, filenames: &[String]) -> llm::CommitStyle {
+    let mut style = if args.history == 0 {
+        llm::CommitStyle::default()
+    } else {
+        let paths = if args.history_paths { filenames } else { &[] };
+        llm::CommitStyle::from_history(&git::git_log_messages(args.history, paths))
+    };
+    if !args.scopes.is_empty() {
+        style.scopes = args.scopes.clone();
+    }
+    style
 }
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::args::CommandArgs;
+use crate::llm::{self, LLMResult, TokenUsage};
+
+/// Generated commit messages as printed by `--output json`, for scripts and editor plugins
+#[derive(Serialize)]
+struct Report<'a> {
+    vendor: Option<&'a str>,
+    model: String,
+    latency_ms: u128,
+    usage: TokenUsage,
+    candidates: Vec<Candidate<'a>>,
+    /// Option accepted with `--yes`, starting at 1
+    selected: Option<u8>,
+}
+
+#[derive(Serialize)]
+struct Candidate<'a> {
+    r#type: &'a str,
+    scope: Option<&'a str>,
+    subject: &'a str,
+    body: Option<&'a str>,
+    footer: Option<&'a str>,
+    /// The message with the reference, the trailers are only added when it is committed
+    message: &'a str,
+}
+
+/// Prints the candidates, token usage, latency and model of a generation as one JSON document
+pub fn print_json(result: &LLMResult, latency: Duration, args: &CommandArgs) -> anyhow::Result<()> {
+    let report = report(result, latency, args.vendor.as_deref(), llm::model_name(args)?, args.yes);
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+fn report<'a>(
+    result: &'a LLMResult,
+    latency: Duration,
+    vendor: Option<&'a str>,
+    model: String,
+    selected: Option<u8>,
+) -> Report<'a> {
+    let candidates = result
+        .candidates
+        .iter()
+        .zip(&result.commit_messages)
+        .map(|(commit, message)| Candidate {
+            r#type: &commit.r#type,
+            scope: commit.scope.as_deref().filter(|s| !s.trim().is_empty()),
+            subject: commit.subject.trim(),
+            body: commit.body.as_deref().filter(|s| !s.trim().is_empty()),
+            footer: commit.footer.as_deref().filter(|s| !s.trim().is_empty()),
+            message,
+        })
+        .collect();
+    Report {
+        vendor,
+        model,
+        latency_ms: latency.as_millis(),
+        usage: result.usage(),
+        candidates,
+        selected,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_report() {
+        let commit: llm::CommitMessage =
+            serde_json::from_str(r#"{"type": "feat", "scope": "cli", "subject": "add --yes", "body": ""}"#).unwrap();
+        let result = LLMResult {
+            commit_message: String::new(),
+            commit_messages: vec!["feat(cli): add --yes #12".to_string()],
+            candidates: vec![commit],
+            completion_tokens: 20,
+            prompt_tokens: 100,
+            total_tokens: 120,
+            retries: 1,
+            commit_files: vec![],
+            messages: vec![],
+            response_schema: None,
+        };
+        let report = report(&result, Duration::from_millis(1500), Some("openai"), "gpt-4o-mini".to_string(), Some(1));
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "vendor": "openai",
+                "model": "gpt-4o-mini",
+                "latency_ms": 1500,
+                "usage": {"completion_tokens": 20, "prompt_tokens": 100, "total_tokens": 120, "retries": 1},
+                "candidates": [{
+                    "type": "feat",
+                    "scope": "cli",
+                    "subject": "add --yes",
+                    "body": null,
+                    "footer": null,
+                    "message": "feat(cli): add --yes #12"
+                }],
+                "selected": 1
+            })
+        );
+    }
+}
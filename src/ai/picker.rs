@@ -0,0 +1,518 @@
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::io::IsTerminal;
+
+use crate::ai::git;
+use crate::conventional::lint::{self, LintRules, Severity, Violation};
+
+/// Lines scrolled by PageUp and PageDown in the diff pane
+const DIFF_PAGE: u16 = 10;
+
+/// Answer of the picker
+pub enum Picked {
+    Commit(String),
+    /// Open the message in the git editor before committing it
+    Edit(String),
+    /// Regenerate the commit messages, with optional free-text feedback for the model
+    Retry(Option<String>),
+    /// Files were left out or staged again, generate messages for the new staged diff
+    Restaged,
+    Exit,
+}
+
+/// Whether the full-screen picker can be used, otherwise the line prompt is
+pub fn is_available() -> bool {
+    std::io::stdout().is_terminal() && std::io::stdin().is_terminal()
+}
+
+/// The staged files and the index they were staged in, files can be left out of the commit and staged again
+pub struct Staging {
+    tree: String,
+    /// Staged paths and whether they are still included
+    files: Vec<(String, bool)>,
+}
+
+impl Staging {
+    pub fn new(files: Vec<String>) -> Result<Staging> {
+        Ok(Staging {
+            tree: git::git_write_tree()?,
+            files: files.into_iter().map(|f| (f, true)).collect(),
+        })
+    }
+
+    fn included(&self) -> Vec<String> {
+        self.files.iter().filter(|(_, i)| *i).map(|(f, _)| f.clone()).collect()
+    }
+
+    fn excluded(&self) -> Vec<String> {
+        self.files.iter().filter(|(_, i)| !*i).map(|(f, _)| f.clone()).collect()
+    }
+
+    /// Leaves a file out of the index, or stages it again as it was staged
+    fn toggle(&mut self, idx: usize) -> Result<()> {
+        if let Some((_, included)) = self.files.get_mut(idx) {
+            *included = !*included;
+            git::git_reset_index()?;
+            git::git_stage_from_tree(&self.tree, &self.included())?;
+        }
+        Ok(())
+    }
+
+    /// Stages the files left out again, after the commit they stay staged for the next one
+    pub fn restore(&mut self) -> Result<()> {
+        git::git_stage_from_tree(&self.tree, &self.excluded())?;
+        self.files.iter_mut().for_each(|(_, included)| *included = true);
+        Ok(())
+    }
+}
+
+/// Shows the commit messages with their lint violations next to the diff, and lets the user pick, edit or
+/// regenerate them. Staged files can be left out when `staging` is given.
+pub fn pick(messages: &[String], rules: &LintRules, diff: &str, staging: Option<&mut Staging>) -> Result<Picked> {
+    let mut picker = Picker::new(messages, rules, diff, staging);
+    let mut terminal = ratatui::init();
+    let picked = run(&mut terminal, &mut picker);
+    ratatui::restore();
+    picked
+}
+
+fn run(terminal: &mut DefaultTerminal, picker: &mut Picker) -> Result<Picked> {
+    loop {
+        terminal.draw(|frame| picker.render(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(picked) = picker.handle_key(key)? {
+                return Ok(picked);
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Focus {
+    Messages,
+    Files,
+}
+
+enum Mode {
+    Browse,
+    /// Inline editing of the selected message
+    Edit(TextEdit),
+    /// Typing feedback for the next generation
+    Feedback(String),
+}
+
+struct Picker<'a> {
+    messages: Vec<String>,
+    violations: Vec<Vec<Violation>>,
+    rules: &'a LintRules,
+    selected: usize,
+    diff: &'a str,
+    diff_scroll: u16,
+    staging: Option<&'a mut Staging>,
+    /// Cursor in the file list
+    file: usize,
+    /// Files were toggled since the messages were generated
+    restaged: bool,
+    focus: Focus,
+    mode: Mode,
+    status: String,
+}
+
+impl<'a> Picker<'a> {
+    fn new(messages: &[String], rules: &'a LintRules, diff: &'a str, staging: Option<&'a mut Staging>) -> Picker<'a> {
+        Picker {
+            messages: messages.to_vec(),
+            violations: messages.iter().map(|m| lint::lint(m, rules)).collect(),
+            rules,
+            selected: 0,
+            diff,
+            diff_scroll: 0,
+            staging,
+            file: 0,
+            restaged: false,
+            focus: Focus::Messages,
+            mode: Mode::Browse,
+            status: String::new(),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Result<Option<Picked>> {
+        match &mut self.mode {
+            Mode::Edit(editor) => {
+                if key.code == KeyCode::Esc {
+                    self.messages[self.selected] = editor.text();
+                    self.violations[self.selected] = lint::lint(&self.messages[self.selected], self.rules);
+                    self.mode = Mode::Browse;
+                } else {
+                    editor.handle_key(key);
+                }
+                return Ok(None);
+            }
+            Mode::Feedback(feedback) => {
+                match key.code {
+                    KeyCode::Esc => self.mode = Mode::Browse,
+                    KeyCode::Enter => {
+                        let feedback = Some(feedback.trim().to_string()).filter(|f| !f.is_empty());
+                        self.mode = Mode::Browse;
+                        return Ok(Some(Picked::Retry(feedback)));
+                    }
+                    KeyCode::Backspace => {
+                        feedback.pop();
+                    }
+                    KeyCode::Char(c) => feedback.push(c),
+                    _ => {}
+                }
+                return Ok(None);
+            }
+            Mode::Browse => {}
+        }
+
+        self.status.clear();
+        let files = self.staging.as_ref().map_or(0, |s| s.files.len());
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(Some(Picked::Exit)),
+            // nothing to pick, only regenerating or leaving make sense
+            KeyCode::Enter | KeyCode::Char('E' | 'e') if self.messages.is_empty() => {}
+            KeyCode::Enter => return Ok(Some(Picked::Commit(self.messages[self.selected].clone()))),
+            KeyCode::Char('E') => return Ok(Some(Picked::Edit(self.messages[self.selected].clone()))),
+            KeyCode::Char('e') => self.mode = Mode::Edit(TextEdit::new(&self.messages[self.selected])),
+            KeyCode::Char('f') => self.mode = Mode::Feedback(String::new()),
+            KeyCode::Char('r') if self.staging.as_ref().is_some_and(|s| s.included().is_empty()) => {
+                self.status = "Nothing is staged, include a file first".to_string();
+            }
+            KeyCode::Char('r') if self.restaged => return Ok(Some(Picked::Restaged)),
+            KeyCode::Char('r') => return Ok(Some(Picked::Retry(None))),
+            KeyCode::Tab if files > 0 => {
+                self.focus = match self.focus {
+                    Focus::Messages => Focus::Files,
+                    Focus::Files => Focus::Messages,
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => match self.focus {
+                Focus::Messages => self.selected = self.selected.saturating_sub(1),
+                Focus::Files => self.file = self.file.saturating_sub(1),
+            },
+            KeyCode::Down | KeyCode::Char('j') => match self.focus {
+                Focus::Messages => self.selected = (self.selected + 1).min(self.messages.len().saturating_sub(1)),
+                Focus::Files => self.file = (self.file + 1).min(files.saturating_sub(1)),
+            },
+            KeyCode::Char(' ') if self.focus == Focus::Files => {
+                if let Some(staging) = self.staging.as_mut() {
+                    staging.toggle(self.file)?;
+                    self.restaged = true;
+                    self.status = "Staged files changed, press r to regenerate the messages".to_string();
+                }
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let idx = c as usize - '1' as usize;
+                if idx < self.messages.len() {
+                    self.selected = idx;
+                }
+            }
+            KeyCode::PageDown => self.diff_scroll = self.diff_scroll.saturating_add(DIFF_PAGE),
+            KeyCode::PageUp => self.diff_scroll = self.diff_scroll.saturating_sub(DIFF_PAGE),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let [main, help] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, diff] = Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(main);
+        let file_rows = self.staging.as_ref().map_or(0, |s| s.files.len().min(8) as u16 + 2);
+        let [candidates, message, files] = Layout::vertical([
+            Constraint::Length(self.messages.len() as u16 + 2),
+            Constraint::Min(5),
+            Constraint::Length(file_rows),
+        ])
+        .areas(left);
+
+        self.render_candidates(frame, candidates);
+        self.render_message(frame, message);
+        if let Some(staging) = self.staging.as_ref() {
+            render_files(frame, files, staging, self.file, self.focus == Focus::Files);
+        }
+        frame.render_widget(
+            Paragraph::new(diff_text(self.diff))
+                .block(Block::bordered().title(" Diff (PgUp/PgDn) "))
+                .scroll((self.diff_scroll, 0)),
+            diff,
+        );
+        frame.render_widget(Paragraph::new(self.help()), help);
+    }
+
+    fn render_candidates(&self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .messages
+            .iter()
+            .zip(&self.violations)
+            .enumerate()
+            .map(|(idx, (message, violations))| {
+                let mut spans = vec![
+                    Span::styled(format!("{} ", idx + 1), Style::new().fg(Color::Cyan)),
+                    Span::raw(message.lines().next().unwrap_or_default().to_string()),
+                ];
+                if violations.iter().any(|v| v.severity == Severity::Error) {
+                    spans.push(Span::styled(" ✗", Style::new().fg(Color::Red)));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let border = focused_border(self.focus == Focus::Messages);
+        let list = List::new(items)
+            .block(Block::bordered().title(" Commit Messages ").border_style(border))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn render_message(&self, frame: &mut Frame, area: Rect) {
+        if let Mode::Edit(editor) = &self.mode {
+            let block = Block::bordered()
+                .title(" Editing (Esc: done) ")
+                .border_style(Style::new().fg(Color::Yellow));
+            let inner = block.inner(area);
+            frame.render_widget(Paragraph::new(editor.lines.join("\n")).block(block), area);
+            let (row, col) = editor.cursor();
+            frame.set_cursor_position((inner.x + col as u16, inner.y + row as u16));
+            return;
+        }
+
+        let mut text = Text::from(self.messages[self.selected].as_str());
+        if let Some(violations) = self.violations.get(self.selected).filter(|v| !v.is_empty()) {
+            text.push_line(Line::default());
+            for violation in violations {
+                let (label, color) = match violation.severity {
+                    Severity::Error => ("error", Color::Red),
+                    Severity::Warning => ("warning", Color::Yellow),
+                };
+                text.push_line(Line::styled(
+                    format!("{}[{}] line {}: {}", label, violation.rule, violation.line, violation.message),
+                    Style::new().fg(color),
+                ));
+            }
+        }
+        frame.render_widget(
+            Paragraph::new(text)
+                .block(Block::bordered().title(format!(" Option {} ", self.selected + 1)))
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+
+    fn help(&self) -> Line<'static> {
+        let text = match &self.mode {
+            Mode::Edit(_) => "Esc done editing".to_string(),
+            Mode::Feedback(feedback) => format!("Feedback: {}▏ (Enter regenerate • Esc back)", feedback),
+            Mode::Browse if !self.status.is_empty() => self.status.clone(),
+            Mode::Browse => {
+                let files = match self.staging {
+                    Some(_) => " • Tab files • Space include/exclude",
+                    None => "",
+                };
+                format!("↑↓ select • Enter commit • e edit • E editor • r regenerate • f feedback{} • q cancel", files)
+            }
+        };
+        Line::styled(text, Style::new().fg(Color::Yellow))
+    }
+}
+
+fn render_files(frame: &mut Frame, area: Rect, staging: &Staging, cursor: usize, focused: bool) {
+    let items: Vec<ListItem> = staging
+        .files
+        .iter()
+        .map(|(file, included)| {
+            let (mark, style) = if *included {
+                ("[x]", Style::new().fg(Color::Green))
+            } else {
+                ("[ ]", Style::new().fg(Color::DarkGray))
+            };
+            ListItem::new(Line::styled(format!("{} {}", mark, file), style))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::bordered().title(" Staged Files ").border_style(focused_border(focused)))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(focused.then_some(cursor));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn focused_border(focused: bool) -> Style {
+    if focused {
+        Style::new().fg(Color::Cyan)
+    } else {
+        Style::new()
+    }
+}
+
+fn diff_text(diff: &str) -> Text<'_> {
+    diff.lines()
+        .map(|line| {
+            let style = match line {
+                l if l.starts_with("+++") || l.starts_with("---") => Style::new().add_modifier(Modifier::BOLD),
+                l if l.starts_with('+') => Style::new().fg(Color::Green),
+                l if l.starts_with('-') => Style::new().fg(Color::Red),
+                l if l.starts_with("@@") => Style::new().fg(Color::Cyan),
+                _ => Style::new(),
+            };
+            Line::styled(line, style)
+        })
+        .collect()
+}
+
+/// A minimal multi-line text editor, the cursor column counts characters
+struct TextEdit {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+impl TextEdit {
+    fn new(text: &str) -> TextEdit {
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        TextEdit { lines, row: 0, col: 0 }
+    }
+
+    fn text(&self) -> String {
+        self.lines.join("\n").trim_end().to_string()
+    }
+
+    fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    fn width(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    /// Byte offset of the cursor in its line
+    fn offset(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices().nth(self.col).map_or(line.len(), |(idx, _)| idx)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char(c) => {
+                let offset = self.offset();
+                self.lines[self.row].insert(offset, c);
+                self.col += 1;
+            }
+            KeyCode::Enter => {
+                let offset = self.offset();
+                let rest = self.lines[self.row].split_off(offset);
+                self.lines.insert(self.row + 1, rest);
+                self.row += 1;
+                self.col = 0;
+            }
+            KeyCode::Backspace if self.col > 0 => {
+                self.col -= 1;
+                let offset = self.offset();
+                self.lines[self.row].remove(offset);
+            }
+            KeyCode::Backspace if self.row > 0 => {
+                let line = self.lines.remove(self.row);
+                self.row -= 1;
+                self.col = self.width(self.row);
+                self.lines[self.row].push_str(&line);
+            }
+            KeyCode::Delete if self.col < self.width(self.row) => {
+                let offset = self.offset();
+                self.lines[self.row].remove(offset);
+            }
+            KeyCode::Delete if self.row + 1 < self.lines.len() => {
+                let line = self.lines.remove(self.row + 1);
+                self.lines[self.row].push_str(&line);
+            }
+            KeyCode::Left if self.col > 0 => self.col -= 1,
+            KeyCode::Right if self.col < self.width(self.row) => self.col += 1,
+            KeyCode::Up if self.row > 0 => {
+                self.row -= 1;
+                self.col = self.col.min(self.width(self.row));
+            }
+            KeyCode::Down if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.col = self.col.min(self.width(self.row));
+            }
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.width(self.row),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn press(picker: &mut Picker, codes: &[KeyCode]) -> Option<Picked> {
+        codes
+            .iter()
+            .map(|code| picker.handle_key(KeyEvent::from(*code)).unwrap())
+            .last()
+            .flatten()
+    }
+
+    #[test]
+    fn test_picker_keys() {
+        let rules = LintRules::new(vec![]);
+        let messages = ["feat: add parser".to_string(), "fix: crash\n\nbody".to_string()];
+        let mut picker = Picker::new(&messages, &rules, "", None);
+
+        assert!(press(&mut picker, &[KeyCode::Down, KeyCode::Down]).is_none());
+        assert_eq!(picker.selected, 1);
+        // edit the subject of the second message inline
+        press(&mut picker, &[KeyCode::Char('e'), KeyCode::End]);
+        press(&mut picker, &[KeyCode::Backspace; 5]);
+        press(&mut picker, &[KeyCode::Char('b'), KeyCode::Char('u'), KeyCode::Char('g'), KeyCode::Esc]);
+        assert!(matches!(press(&mut picker, &[KeyCode::Enter]), Some(Picked::Commit(m)) if m == "fix: bug\n\nbody"));
+
+        let feedback = [KeyCode::Char('f'), KeyCode::Char('s'), KeyCode::Char('h'), KeyCode::Enter];
+        assert!(matches!(press(&mut picker, &feedback), Some(Picked::Retry(Some(f))) if f == "sh"));
+        assert!(matches!(press(&mut picker, &[KeyCode::Char('r')]), Some(Picked::Retry(None))));
+        assert!(matches!(press(&mut picker, &[KeyCode::Char('1'), KeyCode::Char('E')]), Some(Picked::Edit(m)) if m == "feat: add parser"));
+        assert!(matches!(press(&mut picker, &[KeyCode::Char('q')]), Some(Picked::Exit)));
+    }
+
+    #[test]
+    fn test_text_edit() {
+        let mut editor = TextEdit::new("feat: a\nbody");
+        for code in [KeyCode::End, KeyCode::Enter, KeyCode::Char('x'), KeyCode::Down, KeyCode::Home, KeyCode::Delete] {
+            editor.handle_key(KeyEvent::from(code));
+        }
+        assert_eq!(editor.text(), "feat: a\nx\nody");
+        for code in [KeyCode::Up, KeyCode::Home, KeyCode::Backspace] {
+            editor.handle_key(KeyEvent::from(code));
+        }
+        assert_eq!(editor.text(), "feat: ax\nody");
+        assert_eq!(editor.cursor(), (0, 7));
+    }
+
+    #[test]
+    fn test_render() {
+        let rules = LintRules::new(vec![]);
+        let messages = ["Update readme".to_string()];
+        let picker = Picker::new(&messages, &rules, "+added line\n-removed line", None);
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| picker.render(frame)).unwrap();
+
+        let screen: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert!(screen.contains("1 Update readme ✗"));
+        assert!(screen.contains("error[header-format]"));
+        assert!(screen.contains("+added line"));
+        assert!(screen.contains("Enter commit"));
+    }
+}
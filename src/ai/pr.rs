@@ -0,0 +1,44 @@
+use colored::Colorize;
+use std::time::Instant;
+
+use crate::ai::{commit_style, get_command_message, git, require_text_output, theme};
+use crate::{args, llm};
+
+/// Drafts the title and the description of a pull request of the current branch.
+///
+/// The branch is compared to `base`, by default its upstream or the default branch of `origin`.
+pub fn handler(base: Option<&str>, args: &args::CommandArgs) -> anyhow::Result<()> {
+    require_text_output("pr", args)?;
+    if !git::is_git_directory() {
+        eprintln!("Not git directory");
+        return Ok(());
+    }
+
+    let base = match base {
+        Some(base) => base.to_string(),
+        None => git::git_pr_base()?,
+    };
+    let merge_base = git::git_merge_base(&base)?;
+    let commits = git::git_range_messages(&format!("{}..HEAD", merge_base))?;
+    if commits.is_empty() {
+        eprintln!("No commits on top of {}", base);
+        return Ok(());
+    }
+    let diff_content = git::git_range_diff(&merge_base, &args.ignore)?;
+
+    println!("{}", get_command_message());
+    println!(
+        "{} {} commits on top of {}",
+        "🔀".bright_yellow(),
+        commits.len().to_string().bright_green().bold(),
+        base.bright_cyan()
+    );
+    let style = commit_style(args, &[]);
+    let start = Instant::now();
+    let pull_request = llm::llm_pull_request(&diff_content, &commits, &style, args)?;
+    theme::print_stats(&pull_request.usage, start.elapsed());
+
+    println!("{}\n", pull_request.title.bright_green().bold());
+    println!("{}", pull_request.body);
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use colored::Colorize;
+
+use crate::ai::{commit_style, generate_message, get_command_message, git};
+use crate::args::{self, Output};
+
+/// Regenerates the message of an older commit of the current branch from its changes, and rewrites it in place.
+pub fn handler(rev: &str, args: &args::CommandArgs) -> anyhow::Result<()> {
+    if !git::is_git_directory() {
+        eprintln!("Not git directory");
+        return Ok(());
+    }
+
+    git::git_check_unpublished(&[rev.to_string()])?;
+    let (filenames, diff_content) = git::git_commit_changes(rev, &args.ignore)?;
+    if filenames.is_empty() {
+        eprintln!("{} has no changes to describe", rev);
+        return Ok(());
+    }
+
+    if args.output == Output::Text {
+        println!("{}", get_command_message());
+    }
+    let style = commit_style(args, &filenames);
+    let Some(commit_message) = generate_message(&diff_content, &filenames, args.prompt, &style, None, args)? else {
+        return Ok(());
+    };
+    git::git_rewrite_messages(&[(rev.to_string(), commit_message)], args.dry_run)?;
+    if args.output == Output::Text {
+        println!("{} {}", "Reworded".green(), rev);
+    }
+    Ok(())
+}
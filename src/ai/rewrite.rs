@@ -0,0 +1,156 @@
+use colored::Colorize;
+use std::io::Write;
+use std::time::Instant;
+
+use crate::ai::{commit_style, get_command_message, git, require_text_output, theme};
+use crate::llm::TokenUsage;
+use crate::{args, llm};
+
+/// Width of the message columns of the review table
+const COLUMN_WIDTH: usize = 50;
+
+/// Regenerates the messages of every commit of `<base>..HEAD` from its own diff, and rewrites them in a single
+/// history rewrite once reviewed. Merge commits keep their messages.
+pub fn handler(range: &str, args: &args::CommandArgs) -> anyhow::Result<()> {
+    require_text_output("rewrite-range", args)?;
+    if !git::is_git_directory() {
+        eprintln!("Not git directory");
+        return Ok(());
+    }
+
+    let (base, to) = range.split_once("..").unwrap_or((range, "HEAD"));
+    if !matches!(to, "" | "HEAD") {
+        return Err(anyhow::anyhow!("only ranges ending at HEAD can be rewritten: {}", range));
+    }
+    let commits = git::git_range_commits(&format!("{}..HEAD", base))?;
+    if commits.is_empty() {
+        eprintln!("No commits in {}..HEAD", base);
+        return Ok(());
+    }
+    let hashes: Vec<String> = commits.iter().map(|(hash, _)| hash.clone()).collect();
+    git::git_check_unpublished(&hashes)?;
+
+    println!("{}", get_command_message());
+    let start = Instant::now();
+    let mut usage = TokenUsage::default();
+    let mut rewrites: Vec<(String, String)> = Vec::new();
+    for (idx, (hash, message)) in commits.iter().enumerate() {
+        println!(
+            "\n{} {} {}",
+            format!("[{}/{}]", idx + 1, commits.len()).bright_cyan().bold(),
+            hash.bright_yellow(),
+            message.lines().next().unwrap_or_default()
+        );
+        let (filenames, diff_content) = git::git_commit_changes(hash, &args.ignore)?;
+        if filenames.is_empty() {
+            println!("No changes to describe, keeping the message");
+            continue;
+        }
+        let style = commit_style(args, &filenames);
+        let llm_result = llm::llm_request(&diff_content, args.prompt, &style, args)?;
+        usage += llm_result.usage();
+        if let Some(new_message) = llm_result.commit_messages.into_iter().next() {
+            rewrites.push((hash.clone(), new_message));
+        }
+    }
+    theme::print_stats(&usage, start.elapsed());
+
+    let rows: Vec<(&str, &str, &str)> = rewrites
+        .iter()
+        .map(|(hash, new_message)| {
+            let old_message = commits.iter().find(|(h, _)| h == hash).map_or("", |(_, m)| m.as_str());
+            (hash.as_str(), first_line(old_message), first_line(new_message))
+        })
+        .collect();
+    println!("{}", review_table(&rows));
+    if args.dry_run || (args.yes.is_none() && !confirm(&format!("Rewrite {} commit messages?", rewrites.len()))) {
+        println!("{}", "Cancel rewrite".red());
+        return Ok(());
+    }
+
+    git::git_rewrite_messages(&rewrites, false)?;
+    println!("{} {} commit messages", "Rewrote".green(), rewrites.len());
+    Ok(())
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// The current and the proposed header of each commit, side by side
+fn review_table(rows: &[(&str, &str, &str)]) -> String {
+    let hash_width = rows.iter().map(|(hash, _, _)| hash.len()).max().unwrap_or(0).max("Commit".len());
+    let mut table = format!(
+        "{:hash_width$}  {:COLUMN_WIDTH$}  {}\n",
+        "Commit".bold(),
+        "Current".bold(),
+        "Proposed".bold()
+    );
+    table.push_str(&format!("{}  {}  {}\n", "─".repeat(hash_width), "─".repeat(COLUMN_WIDTH), "─".repeat(COLUMN_WIDTH)));
+    for (hash, current, proposed) in rows {
+        table.push_str(&format!(
+            "{:hash_width$}  {:COLUMN_WIDTH$}  {}\n",
+            hash,
+            truncate(current, COLUMN_WIDTH),
+            truncate(proposed, COLUMN_WIDTH)
+        ));
+    }
+    table
+}
+
+/// Shortens a line to `width` characters, ending with an ellipsis when cut
+fn truncate(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        return line.to_string();
+    }
+    let mut cut: String = line.chars().take(width - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Asks before rewriting history, anything but `y` cancels, and so does a closed stdin
+fn confirm(question: &str) -> bool {
+    print!(
+        "\n{} {} {}\n{} ",
+        "🎯".bright_yellow(),
+        question.bright_cyan().bold(),
+        "(y: rewrite)".bright_green(),
+        "⌲ Enter your choice (default: n): ".bright_yellow()
+    );
+    let mut input = String::new();
+    std::io::stdout().flush().expect("Failed to flush stdout");
+    match std::io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => false,
+        Ok(_) => accepts(&input),
+    }
+}
+
+fn accepts(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_review_table() {
+        colored::control::set_override(false);
+        let long = "x".repeat(60);
+        let table = review_table(&[("a1b2c3d", "wip", "feat(cli): add reword"), ("e4f5a6b", &long, "fix: crash")]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0].trim_end(), format!("Commit   {:50}  Proposed", "Current"));
+        assert_eq!(lines[2], format!("a1b2c3d  {:50}  feat(cli): add reword", "wip"));
+        assert_eq!(lines[3], format!("e4f5a6b  {}…  fix: crash", "x".repeat(49)));
+    }
+
+    #[test]
+    fn test_accepts() {
+        assert!(accepts("y\n"));
+        assert!(accepts("Yes\n"));
+        assert!(!accepts("\n"));
+        assert!(!accepts(""));
+        assert!(!accepts("n\n"));
+        assert!(!accepts("yy\n"));
+    }
+}
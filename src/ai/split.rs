@@ -0,0 +1,132 @@
+use colored::Colorize;
+use std::time::Instant;
+
+use crate::ai::{git, theme};
+use crate::llm::SplitConfirm;
+use crate::{args, llm};
+
+/// Proposes a split of the staged changes into several logical commits, and commits them one by one
+/// by re-staging each group from a snapshot of the index.
+pub fn handler(
+    diff_content: &str,
+    style: &llm::CommitStyle,
+    args: &args::CommandArgs,
+) -> anyhow::Result<()> {
+    let paths = git::git_stage_filenames()?;
+    if paths.len() < 2 {
+        println!("Only one file staged, nothing to split");
+        return Ok(());
+    }
+
+    let start = Instant::now();
+    let mut llm_result = llm::llm_split_request(diff_content, &paths, style, args)?;
+    theme::print_stats(&llm_result.usage(), start.elapsed());
+
+    // --yes accepts the proposed split without asking
+    while args.yes.is_none() {
+        match llm::confirm_split(&llm_result) {
+            SplitConfirm::Commit => break,
+            SplitConfirm::Retry(feedback) => {
+                let start = Instant::now();
+                llm_result = llm::llm_retry(&llm_result, feedback.as_deref(), args)?;
+                theme::print_stats(&llm_result.usage(), start.elapsed());
+            }
+            SplitConfirm::Exit => {
+                println!("{}", "Cancel commit".red());
+                return Ok(());
+            }
+        }
+    }
+
+    let (groups, unassigned) = assign_groups(&paths, &llm_result.commit_files);
+    if !unassigned.is_empty() {
+        println!(
+            "{} Not part of any commit, left staged: {}",
+            "⚠️".bright_yellow(),
+            unassigned.join(", ").bright_yellow()
+        );
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let tree = git::git_write_tree()?;
+    git::git_reset_index()?;
+
+    let mut remaining: Vec<String> = paths.clone();
+    for (idx, files) in groups.iter().enumerate() {
+        let Some(files) = files else {
+            continue;
+        };
+        let result = git::git_stage_from_tree(&tree, files)
+            .and_then(|_| git::git_interpret_trailers(&llm_result.commit_messages[idx], &args.trailers))
+            .and_then(|message| git::git_commit(&message, false));
+        if let Err(e) = result {
+            // put everything which is not committed yet back into the index
+            git::git_stage_from_tree(&tree, &remaining)?;
+            return Err(e);
+        }
+        remaining.retain(|p| !files.contains(p));
+    }
+    git::git_stage_from_tree(&tree, &remaining)?;
+
+    Ok(())
+}
+
+/// Matches the files proposed by the model against the staged paths.
+///
+/// Returns the staged files of each commit, `None` when none of its files is staged,
+/// and the staged paths which are not part of any commit. A path proposed twice belongs to the first commit.
+fn assign_groups(paths: &[String], commit_files: &[Vec<String>]) -> (Vec<Option<Vec<String>>>, Vec<String>) {
+    let mut assigned: Vec<&String> = Vec::new();
+    let groups = commit_files
+        .iter()
+        .map(|files| {
+            let group: Vec<String> = files
+                .iter()
+                .map(|f| f.trim())
+                .filter_map(|f| paths.iter().find(|p| p.as_str() == f))
+                .filter(|p| !assigned.contains(p))
+                .cloned()
+                .collect();
+            assigned.extend(paths.iter().filter(|p| group.contains(p)));
+            if group.is_empty() {
+                None
+            } else {
+                Some(group)
+            }
+        })
+        .collect();
+    let unassigned = paths.iter().filter(|p| !assigned.contains(p)).cloned().collect();
+    (groups, unassigned)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_assign_groups() {
+        let paths = strings(&["src/a.rs", "src/b.rs", "README.md", "Cargo.toml"]);
+        let commit_files = vec![
+            strings(&["src/a.rs", "src/b.rs"]),
+            strings(&["src/b.rs", " README.md", "docs/missing.md"]),
+            strings(&["unknown.rs"]),
+        ];
+
+        let (groups, unassigned) = assign_groups(&paths, &commit_files);
+        assert_eq!(
+            groups,
+            vec![
+                Some(strings(&["src/a.rs", "src/b.rs"])),
+                Some(strings(&["README.md"])),
+                None
+            ]
+        );
+        assert_eq!(unassigned, strings(&["Cargo.toml"]));
+    }
+}
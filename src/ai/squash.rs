@@ -0,0 +1,35 @@
+use std::time::Instant;
+
+use crate::ai::{choose_message, commit_style, get_command_message, git, require_text_output, theme};
+use crate::{args, conventional, llm};
+
+/// Writes one Conventional Commit replacing the commits of `<from>..<to>` when squashing them, `<to>` defaults
+/// to HEAD. The chosen message is printed with the `Co-authored-by` trailers of the commits.
+pub fn handler(range: &str, args: &args::CommandArgs) -> anyhow::Result<()> {
+    require_text_output("squash-msg", args)?;
+    if !git::is_git_directory() {
+        eprintln!("Not git directory");
+        return Ok(());
+    }
+
+    let (from, to) = range.split_once("..").unwrap_or((range, ""));
+    let to = if to.is_empty() { "HEAD" } else { to };
+    let commits = git::git_range_messages(&format!("{}..{}", from, to))?;
+    if commits.is_empty() {
+        eprintln!("No commits in {}..{}", from, to);
+        return Ok(());
+    }
+    let (filenames, diff_content) = git::git_squash_changes(from, to, &args.ignore)?;
+
+    println!("{}", get_command_message());
+    let style = commit_style(args, &filenames);
+    let start = Instant::now();
+    let llm_result = llm::llm_squash_request(&diff_content, &commits, &style, args)?;
+    theme::print_stats(&llm_result.usage(), start.elapsed());
+    let Some(message) = choose_message(llm_result, &diff_content, &filenames, None, args)? else {
+        return Ok(());
+    };
+
+    println!("{}", conventional::append_trailers(&message, &conventional::co_authors(&commits)));
+    Ok(())
+}
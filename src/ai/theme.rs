@@ -1,20 +1,23 @@
 use crate::ai;
-use crate::llm::LLMResult;
+use crate::llm::TokenUsage;
 use std::time::Duration;
 
-pub fn print_stats(llm_result: &LLMResult, duration: Duration) {
+pub fn print_stats(usage: &TokenUsage, duration: Duration) {
     let separator = ai::get_stats_separator();
     let mut stats = Vec::new();
     if let Some(stat) = ai::format_stat("Duration", duration.as_millis() as i64, "⏱️") {
         stats.push(stat);
     }
-    if let Some(stat) = ai::format_stat("Usage", llm_result.total_tokens, "💰") {
+    if let Some(stat) = ai::format_stat("Usage", usage.total_tokens, "💰") {
         stats.push(stat);
     }
-    if let Some(stat) = ai::format_stat("Completion", llm_result.completion_tokens, "🎯") {
+    if let Some(stat) = ai::format_stat("Completion", usage.completion_tokens, "🎯") {
         stats.push(stat);
     }
-    if let Some(stat) = ai::format_stat("Prompt Tokens", llm_result.prompt_tokens, "🔤") {
+    if let Some(stat) = ai::format_stat("Prompt Tokens", usage.prompt_tokens, "🔤") {
+        stats.push(stat);
+    }
+    if let Some(stat) = ai::format_stat("Retries", usage.retries as i64, "🔁") {
         stats.push(stat);
     }
 
@@ -0,0 +1,69 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+use crate::ai::git;
+use crate::config::ResolvedConfig;
+
+/// Returns the trailers added after the body of generated messages: the issue of the branch, the co-authors of
+/// `--pair`, then the sign-off of the git user.
+pub fn collect(resolved: &ResolvedConfig, pair: &[String]) -> Result<Vec<String>> {
+    let mut trailers = Vec::new();
+    if let (Some(pattern), Some(branch)) = (&resolved.issue_pattern.value, git::git_current_branch()) {
+        if let Some(key) = issue_key(pattern, &branch)? {
+            trailers.push(format!("{}: {}", resolved.issue_trailer.value, key));
+        }
+    }
+    for member in pair {
+        trailers.push(format!("Co-authored-by: {}", co_author(member, &resolved.roster.value)?));
+    }
+    if resolved.signoff.value {
+        let (Some(name), Some(email)) = (git::git_config("user.name"), git::git_config("user.email")) else {
+            return Err(anyhow!("signing off needs user.name and user.email in the git configuration"));
+        };
+        trailers.push(format!("Signed-off-by: {} <{}>", name, email));
+    }
+    Ok(trailers)
+}
+
+/// The issue key of a branch name: the first group of the pattern, or its whole match without groups
+fn issue_key(pattern: &str, branch: &str) -> Result<Option<String>> {
+    let re = Regex::new(pattern).map_err(|e| anyhow!("invalid issue_pattern `{}`: {}", pattern, e))?;
+    Ok(re
+        .captures(branch)
+        .and_then(|c| c.get(1).or_else(|| c.get(0)))
+        .map(|m| m.as_str().to_string()))
+}
+
+/// `Name <email>` of a pair, given as an alias of the roster or literally
+fn co_author(member: &str, roster: &BTreeMap<String, String>) -> Result<String> {
+    let member = member.trim();
+    if let Some(identity) = roster.get(member) {
+        return Ok(identity.clone());
+    }
+    if member.contains('<') && member.ends_with('>') {
+        return Ok(member.to_string());
+    }
+    Err(anyhow!(
+        "unknown pair `{}`, add it to [commit.roster] or give it as `Name <email>`",
+        member
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_issue_key_and_co_author() {
+        assert_eq!(issue_key(r"[A-Z]+-\d+", "feature/PROJ-123-foo").unwrap().as_deref(), Some("PROJ-123"));
+        assert_eq!(issue_key(r"^\w+/(\d+)-", "fix/42-crash").unwrap().as_deref(), Some("42"));
+        assert_eq!(issue_key(r"[A-Z]+-\d+", "main").unwrap(), None);
+        assert!(issue_key("(", "main").is_err());
+
+        let roster = BTreeMap::from([("ana".to_string(), "Ana Silva <ana@example.com>".to_string())]);
+        assert_eq!(co_author("ana", &roster).unwrap(), "Ana Silva <ana@example.com>");
+        assert_eq!(co_author(" Bo <bo@example.com>", &roster).unwrap(), "Bo <bo@example.com>");
+        assert!(co_author("bo", &roster).is_err());
+    }
+}
@@ -1,4 +1,15 @@
 use crate::prompt::Prompt;
+use clap::ValueEnum;
+
+/// Format of the generated commit messages on stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Output {
+    /// Decorated output and interactive prompts
+    #[default]
+    Text,
+    /// One JSON document with the candidates, token usage, latency and model
+    Json,
+}
 
 pub struct CommandArgs {
     pub push: bool,
@@ -10,9 +21,29 @@ pub struct CommandArgs {
     pub number_of_commit_options: u8,
     pub reference: Option<String>,
     pub language: String,
+    pub split: bool,
+    pub history: usize,
+    pub history_paths: bool,
+    /// Custom prompt template, replaces the built-in template of `prompt`
+    pub prompt_template: Option<String>,
+    /// Allowed commit scopes, see [`crate::config::CommitConfig::scopes`]
+    pub scopes: Vec<String>,
+    /// Files left out of the diff, in gitignore syntax
+    pub ignore: Vec<String>,
+    /// Regenerate the message of the HEAD commit and amend it
+    pub amend: bool,
+    /// Trailers added after the body of the chosen message when committing it, e.g. `Signed-off-by: Name <email>`.
+    /// Only collected for the commands creating new commits.
+    pub trailers: Vec<String>,
+    /// Open the chosen message in the editor before committing it
+    pub edit: bool,
+    /// Accept this option without asking, starting at 1
+    pub yes: Option<u8>,
+    pub output: Output,
 }
 
 impl CommandArgs {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         push: bool,
         dry_run: bool,
@@ -23,6 +54,17 @@ impl CommandArgs {
         number_of_commit_options: u8,
         reference: Option<String>,
         language: String,
+        split: bool,
+        history: usize,
+        history_paths: bool,
+        prompt_template: Option<String>,
+        scopes: Vec<String>,
+        ignore: Vec<String>,
+        amend: bool,
+        trailers: Vec<String>,
+        edit: bool,
+        yes: Option<u8>,
+        output: Output,
     ) -> Self {
         Self {
             push,
@@ -34,6 +76,17 @@ impl CommandArgs {
             number_of_commit_options,
             reference,
             language,
+            split,
+            history,
+            history_paths,
+            prompt_template,
+            scopes,
+            ignore,
+            amend,
+            trailers,
+            edit,
+            yes,
+            output,
         }
     }
 }
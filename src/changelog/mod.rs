@@ -0,0 +1,148 @@
+use crate::conventional::ConventionalCommit;
+
+/// Sections of Keep a Changelog and the commit types listed in them, other types are only listed when breaking
+const SECTIONS: [(&str, &[&str]); 3] = [
+    ("Added", &["feat"]),
+    ("Changed", &["perf", "refactor", "revert"]),
+    ("Fixed", &["fix"]),
+];
+
+/// Start of a new changelog file
+const PREAMBLE: &str = "# Changelog\n\n\
+All notable changes to this project will be documented in this file.\n\n\
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).\n";
+
+/// A commit of the release and its abbreviated hash
+pub struct Entry {
+    pub hash: String,
+    pub commit: ConventionalCommit,
+}
+
+/// Renders a release in Keep a Changelog style.
+///
+/// # Arguments
+/// * `version` - Tag and date of the release, `None` for unreleased changes
+/// * `entries` - Commits of the release, oldest first
+pub fn render(version: Option<(&str, &str)>, entries: &[Entry]) -> String {
+    let mut out = match version {
+        Some((tag, date)) => format!("## [{}] - {}\n", tag, date),
+        None => "## [Unreleased]\n".to_string(),
+    };
+
+    let breaking: Vec<String> = entries
+        .iter()
+        .filter_map(|e| e.commit.breaking_note().map(|note| format_entry(e, note)))
+        .collect();
+    push_section(&mut out, "Breaking Changes", breaking);
+
+    for (section, types) in SECTIONS {
+        let mut listed: Vec<&Entry> = entries
+            .iter()
+            .filter(|e| types.contains(&e.commit.r#type.as_str()))
+            .collect();
+        // group by scope, unscoped entries first, keeping the commit order within a scope
+        listed.sort_by(|a, b| a.commit.scope.cmp(&b.commit.scope));
+        push_section(
+            &mut out,
+            section,
+            listed.iter().map(|e| format_entry(e, &e.commit.description)).collect(),
+        );
+    }
+    out
+}
+
+fn push_section(out: &mut String, title: &str, lines: Vec<String>) {
+    if lines.is_empty() {
+        return;
+    }
+    out.push_str(&format!("\n### {}\n\n", title));
+    for line in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+}
+
+/// `- **scope:** text (hash)`, following lines of the text are indented under the bullet
+fn format_entry(entry: &Entry, text: &str) -> String {
+    let text = text.lines().map(str::trim).collect::<Vec<_>>().join("\n  ");
+    match &entry.commit.scope {
+        Some(scope) => format!("- **{}:** {} ({})", scope, text, entry.hash),
+        None => format!("- {} ({})", text, entry.hash),
+    }
+}
+
+/// Puts a release in front of the releases of an existing changelog, after its preamble
+pub fn prepend(changelog: &str, release: &str) -> String {
+    if changelog.trim().is_empty() {
+        return format!("{}\n{}", PREAMBLE, release);
+    }
+
+    let release = format!("{}\n", release.trim_end());
+    let mut offset = 0;
+    for line in changelog.split_inclusive('\n') {
+        if line.starts_with("## ") {
+            return format!("{}{}\n{}", &changelog[..offset], release, &changelog[offset..]);
+        }
+        offset += line.len();
+    }
+    format!("{}\n\n{}", changelog.trim_end(), release)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entries(commits: &[(&str, &str)]) -> Vec<Entry> {
+        commits
+            .iter()
+            .map(|(hash, message)| Entry {
+                hash: hash.to_string(),
+                commit: ConventionalCommit::parse(message).unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render() {
+        let entries = entries(&[
+            ("a1", "fix(parser): handle trailing commas"),
+            ("b2", "feat(cli)!: require a vendor\n\nBREAKING CHANGE: `--vendor` has no default\nany more"),
+            ("c3", "chore: bump deps"),
+            ("d4", "feat: add changelog command"),
+            ("e5", "perf(parser): avoid copies"),
+            ("f6", "feat(cli): add --base"),
+        ]);
+        assert_eq!(
+            render(Some(("v1.0.0", "2026-10-18")), &entries),
+            "## [v1.0.0] - 2026-10-18\n\n\
+            ### Breaking Changes\n\n\
+            - **cli:** `--vendor` has no default\n  any more (b2)\n\n\
+            ### Added\n\n\
+            - add changelog command (d4)\n\
+            - **cli:** require a vendor (b2)\n\
+            - **cli:** add --base (f6)\n\n\
+            ### Changed\n\n\
+            - **parser:** avoid copies (e5)\n\n\
+            ### Fixed\n\n\
+            - **parser:** handle trailing commas (a1)\n"
+        );
+        assert_eq!(render(None, &[]), "## [Unreleased]\n");
+    }
+
+    #[test]
+    fn test_prepend() {
+        let release = "## [v1.1.0] - 2026-10-18\n\n### Fixed\n\n- crash (a1)\n";
+        let existing = "# Changelog\n\nNotes.\n\n## [v1.0.0] - 2026-01-01\n\n### Added\n\n- all (b2)\n";
+        assert_eq!(
+            prepend(existing, release),
+            "# Changelog\n\nNotes.\n\n## [v1.1.0] - 2026-10-18\n\n### Fixed\n\n- crash (a1)\n\n\
+            ## [v1.0.0] - 2026-01-01\n\n### Added\n\n- all (b2)\n"
+        );
+        assert_eq!(
+            prepend("# Changelog\n", release),
+            format!("# Changelog\n\n{}", release)
+        );
+        assert!(prepend("", release).starts_with("# Changelog\n\nAll notable changes"));
+        assert!(prepend("", release).ends_with(&format!(".\n\n{}", release)));
+    }
+}
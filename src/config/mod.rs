@@ -1,19 +1,30 @@
-use crate::llm::PromptModelVendor;
+use crate::llm;
+use crate::llm::{PromptModelVendor, ProviderKind};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 
+pub use resolve::{resolve, Overrides, ResolvedConfig};
+
+mod resolve;
 mod storage;
+#[cfg(test)]
 mod vendor;
 
 /// Update or create configuration for a specific model
-pub fn handler(vendor: &PromptModelVendor, api_key: &str, model: String) -> Result<()> {
-    let mut config = GlobalConfig::load().unwrap_or_else(|| create_default_config());
+pub fn handler(vendor: &PromptModelVendor, api_key: &str, model: Option<String>) -> Result<()> {
+    let mut config = GlobalConfig::load().unwrap_or_else(GlobalConfig::new);
 
+    let base_url = get_default_base_url(vendor);
+    let model = model.unwrap_or_else(|| llm::default_model(vendor, &base_url));
     let model_config = ModelConfig {
         api_key: Some(api_key.to_string()),
         model: model.to_string(),
-        base_url: get_default_base_url(vendor),
+        base_url,
+        kind: vendor.kind(),
+        structured_output: false,
+        http: HttpConfig::default(),
     };
 
     config.vendors.insert(model, model_config);
@@ -28,6 +39,7 @@ fn get_default_base_url(vendor: &PromptModelVendor) -> String {
         PromptModelVendor::OpenAI => "https://api.openai.com/v1".to_string(),
         PromptModelVendor::DeepSeek => "https://api.deepseek.com/v1".to_string(),
         PromptModelVendor::Ollama => "http://localhost:11434".to_string(),
+        PromptModelVendor::Anthropic => "https://api.anthropic.com/v1".to_string(),
     }
 }
 
@@ -36,10 +48,15 @@ fn create_default_config() -> GlobalConfig {
     GlobalConfig {
         default: DefaultConfig {
             default_vendor: String::from("ollama"),
-            timeout: 30,
+            timeout: 120,
+            connect_timeout: default_connect_timeout(),
+            max_attempts: default_max_attempts(),
             // number_of_commit_options: 3,
         },
         vendors: HashMap::new(),
+        diff: DiffConfig::default(),
+        redact: RedactConfig::default(),
+        commit: CommitConfig::default(),
         model_parameters: Some(ModelParameters {
             temperature: 0.1,
             top_p: 0.75,
@@ -67,6 +84,18 @@ pub struct GlobalConfig {
     #[serde(rename = "vendor", default = "HashMap::new")]
     pub vendors: HashMap<String, ModelConfig>,
 
+    /// Settings for sending the staged diff to the model
+    #[serde(default)]
+    pub diff: DiffConfig,
+
+    /// Secret redaction of the staged diff
+    #[serde(default)]
+    pub redact: RedactConfig,
+
+    /// Commit message conventions, a repository `.gitbuddy.toml` overrides them per project
+    #[serde(default)]
+    pub commit: CommitConfig,
+
     /// Model parameters for inference
     pub model_parameters: Option<ModelParameters>,
 }
@@ -134,12 +163,15 @@ pub struct ModelConfig {
     pub model: String,
     /// Base URL for API requests
     pub base_url: String,
-}
-
-impl ModelConfig {
-    pub fn must_api_key(&self) -> String {
-        self.api_key.clone().unwrap_or(String::new())
-    }
+    /// API protocol of the vendor, defaults to OpenAI-compatible
+    #[serde(default)]
+    pub kind: ProviderKind,
+    /// Whether the vendor honours `json_schema` structured outputs, the response is repaired otherwise
+    #[serde(default)]
+    pub structured_output: bool,
+    /// Timeouts and retries of the requests, taken from the `[default]` table
+    #[serde(skip)]
+    pub http: HttpConfig,
 }
 
 /// Default configuration settings
@@ -147,13 +179,149 @@ impl ModelConfig {
 pub struct DefaultConfig {
     /// Default model vendor to use
     pub default_vendor: String,
-    /// Request timeout in seconds
+    /// Read timeout in seconds, the longest wait for the next bytes of a response
     pub timeout: u64,
+    /// Connect timeout in seconds
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    /// Attempts of a request failing with 429, 5xx or a reset connection, the first one included
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
     // Number of commit options, defaults to 3
     // #[serde(default = )]
     // pub number_of_commit_options: u8,
 }
 
+fn default_connect_timeout() -> u64 {
+    10
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+impl DefaultConfig {
+    /// Timeouts and retries of the requests to the model vendor
+    pub fn http(&self) -> HttpConfig {
+        HttpConfig {
+            connect_timeout: Duration::from_secs(self.connect_timeout),
+            read_timeout: Duration::from_secs(self.timeout),
+            max_attempts: self.max_attempts.max(1),
+        }
+    }
+}
+
+/// Timeouts and retries of the requests to the model vendor
+#[derive(Debug, Clone, Copy)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout: Duration::from_secs(default_connect_timeout()),
+            read_timeout: Duration::from_secs(120),
+            max_attempts: default_max_attempts(),
+        }
+    }
+}
+
+/// Settings for sending the staged diff to the model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffConfig {
+    /// Token budget of a single request, larger diffs are summarised chunk by chunk first
+    pub max_tokens: usize,
+    /// Most chunks summarised with one request each, a larger diff is truncated to the budget instead
+    #[serde(default = "default_max_chunks")]
+    pub max_chunks: usize,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        DiffConfig {
+            max_tokens: 8000,
+            max_chunks: default_max_chunks(),
+        }
+    }
+}
+
+fn default_max_chunks() -> usize {
+    8
+}
+
+/// Files left out of the diff when the configuration defines no `ignore` list:
+/// build artifacts and dependency lock files
+pub const DEFAULT_IGNORE: [&str; 6] = [
+    /* Rust files */
+    "Cargo.lock",
+    /* Node.js files */
+    "node_modules",
+    "dist",
+    "package-lock.json",
+    "pnpm-lock.json",
+    "*.lock",
+];
+
+/// Commit message conventions, shared by the global configuration and the repository `.gitbuddy.toml`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CommitConfig {
+    /// Human-readable language of the messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Number of commit message options
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<u8>,
+    /// Built-in prompt template, e.g. p1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Custom prompt template file, relative to the configuration file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_file: Option<String>,
+    /// Files left out of the diff in gitignore syntax, replaces [`DEFAULT_IGNORE`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<Vec<String>>,
+    /// Allowed commit scopes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+    /// Add a `Signed-off-by` trailer of the git user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signoff: Option<bool>,
+    /// Team members by alias for `--pair`, e.g. `ana = "Ana Silva <ana@example.com>"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roster: Option<BTreeMap<String, String>>,
+    /// Regex finding the issue key in the branch name, its first group or the whole match, e.g. `[A-Z]+-\d+`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue_pattern: Option<String>,
+    /// Token of the issue trailer, `Refs` by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue_trailer: Option<String>,
+}
+
+/// Secret redaction of the staged diff, applied before it is sent to the model
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RedactConfig {
+    /// What to do with secrets found in the diff
+    #[serde(default)]
+    pub mode: RedactMode,
+}
+
+/// What to do with secrets found in the diff
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactMode {
+    /// Replace the secrets by placeholders
+    #[default]
+    Redact,
+    /// Send nothing and fail
+    Refuse,
+    /// Send the diff unchanged
+    Off,
+}
+
 /// Parameters for model inference
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ModelParameters {
@@ -175,12 +343,12 @@ mod test {
 
     #[test]
     fn test_config() {
-        let params = ModelConfig {
+        let _params = ModelConfig {
             model: String::from("gpt-3.5-turbo"),
             api_key: Some(String::from("sk-xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx")),
         };
 
-        let mut cfg = GlobalConfig::new();
+        let cfg = GlobalConfig::new();
         // cfg.set_model(UseModel::DeepSeek(params));
 
         let toml_str = toml::to_string(&cfg).unwrap();
@@ -189,7 +357,7 @@ mod test {
 
     #[test]
     fn config_serialization() {
-        let toml_str = r#"
+        let _toml_str = r#"
 [model.DeepSeek]
 model = "gpt-3.5-turbo"
 api_key = "sk-12345678"
@@ -200,7 +368,7 @@ api_key = "sk-12345678"
 
     #[test]
     fn save_config() {
-        let mut cfg = GlobalConfig::new();
+        let _cfg = GlobalConfig::new();
 
         // let params = OpenAILikeParams {
         //     model: String::from("gpt-3.5-turbo"),
@@ -210,4 +378,28 @@ api_key = "sk-12345678"
         // cfg.set_model(UseModel::DeepSeek(params));
         // cfg.save();
     }
+
+    #[test]
+    fn model_config_kind() {
+        let mc: super::ModelConfig = toml::from_str(
+            r#"
+model = "gpt-4o-mini"
+base_url = "https://api.openai.com/v1"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(mc.kind, ProviderKind::OpenAI);
+
+        let mc: super::ModelConfig = toml::from_str(
+            r#"
+model = "gpt-4o-mini"
+base_url = "https://api.openai.com/v1"
+kind = "openai"
+structured_output = true
+        "#,
+        )
+        .unwrap();
+        assert_eq!(mc.kind, ProviderKind::OpenAI);
+        assert!(mc.structured_output);
+    }
 }
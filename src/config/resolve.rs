@@ -0,0 +1,437 @@
+use crate::ai::git;
+use crate::config::{storage, CommitConfig, GlobalConfig, DEFAULT_IGNORE};
+use crate::prompt::Prompt;
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Name of the repository configuration file, looked up in the root of the working tree
+pub const REPO_CONFIG_FILE_NAME: &str = ".gitbuddy.toml";
+
+/// Repository configuration, `.gitbuddy.toml` in the root of the working tree.
+///
+/// It pins the conventions of a project and is merged over the global configuration.
+/// Vendors and API keys stay in the global configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Vendor used in this repository, must be defined in the global configuration
+    pub vendor: Option<String>,
+    #[serde(default)]
+    pub commit: CommitConfig,
+}
+
+/// Where an effective configuration value comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    Global(PathBuf),
+    Repo(PathBuf),
+    Cli,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Global(path) => write!(f, "global config ({})", path.display()),
+            Source::Repo(path) => write!(f, "repository config ({})", path.display()),
+            Source::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// An effective configuration value and its source
+#[derive(Debug, Clone)]
+pub struct Sourced<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+/// Values given on the command line, they take precedence over both configuration files
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub vendor: Option<String>,
+    pub language: Option<String>,
+    pub number: Option<u8>,
+    pub prompt: Option<Prompt>,
+    pub signoff: Option<bool>,
+}
+
+/// Effective settings: command line, then `.gitbuddy.toml`, then the global configuration, then the defaults.
+///
+/// Lists are not merged, the list of the closest layer replaces the others.
+#[derive(Debug)]
+pub struct ResolvedConfig {
+    pub vendor: Sourced<String>,
+    pub language: Sourced<String>,
+    pub number: Sourced<u8>,
+    pub prompt: Sourced<Prompt>,
+    pub prompt_file: Sourced<Option<PathBuf>>,
+    pub ignore: Sourced<Vec<String>>,
+    pub scopes: Sourced<Vec<String>>,
+    pub signoff: Sourced<bool>,
+    pub roster: Sourced<BTreeMap<String, String>>,
+    pub issue_pattern: Sourced<Option<String>>,
+    pub issue_trailer: Sourced<String>,
+}
+
+impl ResolvedConfig {
+    /// Reads the custom prompt template, if any.
+    ///
+    /// A template of `.gitbuddy.toml` must stay inside the repository once symlinks are resolved: it is sent to the
+    /// vendor as is, a cloned repository must not be able to send any other file of the machine.
+    pub fn prompt_template(&self) -> Result<Option<String>> {
+        let Some(path) = &self.prompt_file.value else {
+            return Ok(None);
+        };
+        if let Source::Repo(config) = &self.prompt_file.source {
+            let root = config.parent().unwrap_or(Path::new(".")).canonicalize()?;
+            let target = path
+                .canonicalize()
+                .with_context(|| format!("read prompt template {}", path.display()))?;
+            if !target.starts_with(&root) {
+                return Err(anyhow!(
+                    "prompt_file {} of {} is outside of the repository",
+                    path.display(),
+                    config.display()
+                ));
+            }
+        }
+        fs::read_to_string(path)
+            .map(Some)
+            .with_context(|| format!("read prompt template {}", path.display()))
+    }
+
+    /// Prints the effective settings, with their sources when `resolved` is set
+    pub fn print(&self, resolved: bool) {
+        let prompt_file = self.prompt_file.value.as_ref().map(|p| p.display().to_string());
+        let rows = [
+            ("vendor", json(&self.vendor.value), &self.vendor.source),
+            ("language", json(&self.language.value), &self.language.source),
+            ("number", json(&self.number.value), &self.number.source),
+            ("prompt", json(&self.prompt.value.to_string()), &self.prompt.source),
+            ("prompt_file", json(&prompt_file), &self.prompt_file.source),
+            ("ignore", json(&self.ignore.value), &self.ignore.source),
+            ("scopes", json(&self.scopes.value), &self.scopes.source),
+            ("signoff", json(&self.signoff.value), &self.signoff.source),
+            ("roster", json(&self.roster.value), &self.roster.source),
+            ("issue_pattern", json(&self.issue_pattern.value), &self.issue_pattern.source),
+            ("issue_trailer", json(&self.issue_trailer.value), &self.issue_trailer.source),
+        ];
+        for (key, value, source) in rows {
+            if resolved {
+                println!(
+                    "{:<13} = {:<30} {}",
+                    key.bright_cyan(),
+                    value.bright_green(),
+                    format!("# {}", source).dimmed()
+                );
+            } else {
+                println!("{:<13} = {}", key.bright_cyan(), value.bright_green());
+            }
+        }
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// Resolves the effective settings of the current repository
+pub fn resolve(overrides: &Overrides) -> Result<ResolvedConfig> {
+    // an unreadable global config is reported and skipped, like GlobalConfig::load does
+    let global = match (storage::config_path(), storage::read_config()) {
+        (Some(path), Some(content)) => match toml::from_str::<GlobalConfig>(&content) {
+            Ok(config) => Some((path, config)),
+            Err(err) => {
+                eprintln!("Load config error: {}", err);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    let repo = match git::git_toplevel() {
+        Ok(root) => load_repo_config(&root)?,
+        Err(_) => None,
+    };
+
+    resolve_layers(overrides, repo.as_ref(), global.as_ref())
+}
+
+/// Reads `.gitbuddy.toml` from the root of the working tree, when present
+pub fn load_repo_config(root: &Path) -> Result<Option<(PathBuf, RepoConfig)>> {
+    let path = root.join(REPO_CONFIG_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)?;
+    let config = toml::from_str(&content).with_context(|| format!("invalid config {}", path.display()))?;
+    Ok(Some((path, config)))
+}
+
+fn resolve_layers(
+    overrides: &Overrides,
+    repo: Option<&(PathBuf, RepoConfig)>,
+    global: Option<&(PathBuf, GlobalConfig)>,
+) -> Result<ResolvedConfig> {
+    let repo_layer = |value: fn(&RepoConfig) -> Option<String>| repo.map(|(path, c)| (path, value(c)));
+    let global_layer = |value: fn(&GlobalConfig) -> Option<String>| global.map(|(path, c)| (path, value(c)));
+
+    let vendor = layered(
+        overrides.vendor.clone(),
+        repo_layer(|c| c.vendor.clone()),
+        global_layer(|c| Some(c.default.default_vendor.clone())),
+    )
+    .unwrap_or_else(|| default_value(GlobalConfig::new().default.default_vendor));
+    let language = layered(
+        overrides.language.clone(),
+        repo_layer(|c| c.commit.language.clone()),
+        global_layer(|c| c.commit.language.clone()),
+    )
+    .unwrap_or_else(|| default_value("English".to_string()));
+    let number = layered(
+        overrides.number,
+        repo.map(|(path, c)| (path, c.commit.number)),
+        global.map(|(path, c)| (path, c.commit.number)),
+    )
+    .unwrap_or_else(|| default_value(1));
+
+    let prompt = match layered(
+        overrides.prompt.map(|p| p.to_string()),
+        repo_layer(|c| c.commit.prompt.clone()),
+        global_layer(|c| c.commit.prompt.clone()),
+    ) {
+        Some(Sourced { value, source }) => Sourced {
+            value: Prompt::from_str(&value, true).map_err(|_| anyhow!("invalid prompt {} from {}", value, source))?,
+            source,
+        },
+        None => default_value(Prompt::P1),
+    };
+
+    // the template path is relative to the file defining it
+    let prompt_file = match layered(
+        None,
+        repo_layer(|c| c.commit.prompt_file.clone()),
+        global_layer(|c| c.commit.prompt_file.clone()),
+    ) {
+        Some(Sourced { value, source }) => {
+            let base = match &source {
+                Source::Repo(path) => {
+                    // checked again on the real path when the template is read
+                    let relative = Path::new(&value);
+                    if relative.is_absolute() || relative.components().any(|c| c == Component::ParentDir) {
+                        return Err(anyhow!(
+                            "prompt_file {} of {} must be a path inside the repository",
+                            value,
+                            path.display()
+                        ));
+                    }
+                    path.parent().map(Path::to_path_buf)
+                }
+                Source::Global(path) => path.parent().map(Path::to_path_buf),
+                _ => None,
+            };
+            Sourced {
+                value: Some(base.unwrap_or_default().join(value)),
+                source,
+            }
+        }
+        None => default_value(None),
+    };
+
+    let list = |value: fn(&CommitConfig) -> Option<Vec<String>>, default: &[&str]| {
+        layered(
+            None,
+            repo.map(|(path, c)| (path, value(&c.commit))),
+            global.map(|(path, c)| (path, value(&c.commit))),
+        )
+        .unwrap_or_else(|| default_value(default.iter().map(|s| s.to_string()).collect()))
+    };
+
+    let signoff = layered(
+        overrides.signoff,
+        repo.map(|(path, c)| (path, c.commit.signoff)),
+        global.map(|(path, c)| (path, c.commit.signoff)),
+    )
+    .unwrap_or_else(|| default_value(false));
+    let roster = layered(
+        None,
+        repo.map(|(path, c)| (path, c.commit.roster.clone())),
+        global.map(|(path, c)| (path, c.commit.roster.clone())),
+    )
+    .unwrap_or_else(|| default_value(BTreeMap::new()));
+    let issue_pattern = match layered(
+        None,
+        repo_layer(|c| c.commit.issue_pattern.clone()),
+        global_layer(|c| c.commit.issue_pattern.clone()),
+    ) {
+        Some(Sourced { value, source }) => Sourced {
+            value: Some(value),
+            source,
+        },
+        None => default_value(None),
+    };
+    let issue_trailer = layered(
+        None,
+        repo_layer(|c| c.commit.issue_trailer.clone()),
+        global_layer(|c| c.commit.issue_trailer.clone()),
+    )
+    .unwrap_or_else(|| default_value("Refs".to_string()));
+
+    Ok(ResolvedConfig {
+        vendor,
+        language,
+        number,
+        prompt,
+        prompt_file,
+        ignore: list(|c| c.ignore.clone(), &DEFAULT_IGNORE),
+        scopes: list(|c| c.scopes.clone(), &[]),
+        signoff,
+        roster,
+        issue_pattern,
+        issue_trailer,
+    })
+}
+
+/// Picks the value of the closest layer defining it: command line, repository, then global configuration
+fn layered<T>(
+    cli: Option<T>,
+    repo: Option<(&PathBuf, Option<T>)>,
+    global: Option<(&PathBuf, Option<T>)>,
+) -> Option<Sourced<T>> {
+    if let Some(value) = cli {
+        return Some(Sourced {
+            value,
+            source: Source::Cli,
+        });
+    }
+    if let Some((path, Some(value))) = repo {
+        return Some(Sourced {
+            value,
+            source: Source::Repo(path.clone()),
+        });
+    }
+    if let Some((path, Some(value))) = global {
+        return Some(Sourced {
+            value,
+            source: Source::Global(path.clone()),
+        });
+    }
+    None
+}
+
+fn default_value<T>(value: T) -> Sourced<T> {
+    Sourced {
+        value,
+        source: Source::Default,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_layers() {
+        let global_path = PathBuf::from("/home/dev/.config/gitbuddy/config.toml");
+        let mut global = GlobalConfig::new();
+        global.default.default_vendor = "openai".to_string();
+        global.commit.language = Some("Chinese".to_string());
+        global.commit.number = Some(3);
+        global.commit.scopes = Some(vec!["global".to_string()]);
+
+        let repo_path = PathBuf::from("/work/project/.gitbuddy.toml");
+        let repo: RepoConfig = toml::from_str(
+            r#"
+vendor = "deepseek"
+
+[commit]
+language = "Japanese"
+prompt_file = ".github/commit-prompt.md"
+scopes = ["parser", "cli"]
+issue_pattern = "[A-Z]+-\\d+"
+
+[commit.roster]
+ana = "Ana Silva <ana@example.com>"
+"#,
+        )
+        .unwrap();
+
+        let overrides = Overrides {
+            language: Some("German".to_string()),
+            ..Default::default()
+        };
+        let resolved =
+            resolve_layers(&overrides, Some(&(repo_path.clone(), repo)), Some(&(global_path.clone(), global))).unwrap();
+
+        assert_eq!(resolved.vendor.value, "deepseek");
+        assert_eq!(resolved.vendor.source, Source::Repo(repo_path.clone()));
+        assert_eq!(resolved.language.value, "German");
+        assert_eq!(resolved.language.source, Source::Cli);
+        assert_eq!(resolved.number.value, 3);
+        assert_eq!(resolved.number.source, Source::Global(global_path));
+        assert_eq!(resolved.prompt.value, Prompt::P1);
+        assert_eq!(resolved.prompt.source, Source::Default);
+        assert_eq!(
+            resolved.prompt_file.value,
+            Some(PathBuf::from("/work/project/.github/commit-prompt.md"))
+        );
+        assert_eq!(resolved.scopes.value, vec!["parser", "cli"]);
+        assert_eq!(resolved.scopes.source, Source::Repo(repo_path));
+        assert_eq!(resolved.ignore.value, DEFAULT_IGNORE);
+        assert_eq!(resolved.ignore.source, Source::Default);
+        assert!(!resolved.signoff.value);
+        assert_eq!(resolved.roster.value["ana"], "Ana Silva <ana@example.com>");
+        assert_eq!(resolved.issue_pattern.value.as_deref(), Some("[A-Z]+-\\d+"));
+        assert_eq!(resolved.issue_trailer.value, "Refs");
+    }
+
+    #[test]
+    fn test_repo_prompt_file_stays_in_repo() {
+        let resolve_repo = |prompt_file: &str, repo_path: &Path| {
+            let repo: RepoConfig = toml::from_str(&format!("[commit]\nprompt_file = {:?}\n", prompt_file)).unwrap();
+            resolve_layers(&Overrides::default(), Some(&(repo_path.to_path_buf(), repo)), None)
+        };
+        let repo_path = Path::new("/work/project/.gitbuddy.toml");
+        for prompt_file in ["/etc/passwd", "../../secret", "docs/../../secret"] {
+            assert!(resolve_repo(prompt_file, repo_path).is_err(), "{}", prompt_file);
+        }
+
+        // a symlink inside the repository pointing outside of it is refused when reading
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret"), "secret").unwrap();
+        let repo = tempfile::tempdir().unwrap();
+        fs::write(repo.path().join("prompt.md"), "prompt").unwrap();
+        let repo_path = repo.path().join(REPO_CONFIG_FILE_NAME);
+        assert_eq!(
+            resolve_repo("prompt.md", &repo_path).unwrap().prompt_template().unwrap().as_deref(),
+            Some("prompt")
+        );
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outside.path().join("secret"), repo.path().join("link.md")).unwrap();
+            let error = resolve_repo("link.md", &repo_path).unwrap().prompt_template().unwrap_err();
+            assert!(error.to_string().contains("outside of the repository"), "{}", error);
+        }
+
+        // the global configuration is trusted
+        let global_path = PathBuf::from("/home/dev/.config/gitbuddy/config.toml");
+        let mut global = GlobalConfig::new();
+        global.commit.prompt_file = Some("/etc/gitbuddy/prompt.md".to_string());
+        let resolved = resolve_layers(&Overrides::default(), None, Some(&(global_path, global))).unwrap();
+        assert_eq!(resolved.prompt_file.value, Some(PathBuf::from("/etc/gitbuddy/prompt.md")));
+    }
+
+    #[test]
+    fn test_repo_config_rejects_vendors() {
+        let result = toml::from_str::<RepoConfig>("[vendors.openai]\napi_key = \"sk-xxx\"\n");
+        assert!(result.is_err());
+    }
+}
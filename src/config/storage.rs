@@ -49,20 +49,14 @@ pub(crate) fn save_config(content: &str) -> Result<()> {
     }
 }
 
+/// path of the config file in the local config dir
+pub(crate) fn config_path() -> Option<PathBuf> {
+    get_config_dir().map(|dir| dir.join(CONFIG_FILE_NAME))
+}
+
 /// read config file from local config dir
 pub(crate) fn read_config() -> Option<String> {
-    let dir = get_config_dir()?;
-
-    let config_file_name = dir.join(CONFIG_FILE_NAME);
-
-    match fs::read_to_string(config_file_name) {
-        Ok(content) => {
-            Some(content)
-        },
-        Err(_) => {
-            None
-        }
-    }
+    fs::read_to_string(config_path()?).ok()
 }
 
 #[cfg(test)]
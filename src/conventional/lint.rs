@@ -0,0 +1,337 @@
+use colored::Colorize;
+use regex::Regex;
+use std::fmt::{Display, Formatter};
+
+use crate::conventional::{ConventionalCommit, COMMIT_TYPES, FOOTER_PATTERN};
+
+/// Maximum length of the header, as recommended by git
+pub const MAX_HEADER_LENGTH: usize = 72;
+/// Maximum length of body and footer lines, the width generated messages are wrapped at
+pub const MAX_BODY_LINE_LENGTH: usize = 100;
+
+/// Past tense and gerund-like words which are verbs in the imperative mood
+const IMPERATIVE_EXCEPTIONS: [&str; 14] = [
+    "embed", "feed", "seed", "shed", "speed", "need", "proceed", "exceed", "succeed", "bring", "string", "ring",
+    "ping", "spring",
+];
+/// Third person forms of verbs commonly used in subjects
+const THIRD_PERSON: [&str; 20] = [
+    "adds",
+    "fixes",
+    "updates",
+    "removes",
+    "changes",
+    "improves",
+    "uses",
+    "makes",
+    "moves",
+    "renames",
+    "refactors",
+    "implements",
+    "supports",
+    "allows",
+    "handles",
+    "creates",
+    "deletes",
+    "bumps",
+    "sets",
+    "returns",
+];
+
+/// Rules of the linter
+#[derive(Debug, Clone)]
+pub struct LintRules {
+    /// Allowed scopes, any scope when empty
+    pub scopes: Vec<String>,
+    pub max_header_length: usize,
+    pub max_body_line_length: usize,
+}
+
+impl LintRules {
+    pub fn new(scopes: Vec<String>) -> LintRules {
+        LintRules {
+            scopes,
+            max_header_length: MAX_HEADER_LENGTH,
+            max_body_line_length: MAX_BODY_LINE_LENGTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A broken rule of a commit message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub severity: Severity,
+    /// Name of the rule, e.g. `type-enum`
+    pub rule: &'static str,
+    /// Line of the message, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl Violation {
+    fn error(rule: &'static str, line: usize, message: String) -> Violation {
+        Violation {
+            severity: Severity::Error,
+            rule,
+            line,
+            message,
+        }
+    }
+
+    fn warning(rule: &'static str, line: usize, message: String) -> Violation {
+        Violation {
+            severity: Severity::Warning,
+            rule,
+            line,
+            message,
+        }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error".bright_red(),
+            Severity::Warning => "warning".bright_yellow(),
+        };
+        write!(f, "{}[{}] line {}: {}", severity, self.rule, self.line, self.message)
+    }
+}
+
+/// Checks a commit message against the Conventional Commits rules, comments must already be stripped
+pub fn lint(message: &str, rules: &LintRules) -> Vec<Violation> {
+    let lines: Vec<&str> = message.trim().lines().collect();
+    let header = lines.first().copied().unwrap_or_default();
+    let Some(commit) = ConventionalCommit::parse(message) else {
+        return vec![Violation::error(
+            "header-format",
+            1,
+            format!("`{}` is not `type(scope): subject`", header),
+        )];
+    };
+
+    let mut violations = Vec::new();
+    if !COMMIT_TYPES.contains(&commit.r#type.as_str()) {
+        violations.push(Violation::error(
+            "type-enum",
+            1,
+            format!("type `{}` is not one of {}", commit.r#type, COMMIT_TYPES.join(", ")),
+        ));
+    }
+    if let Some(scope) = &commit.scope {
+        if !rules.scopes.is_empty() && !rules.scopes.contains(scope) {
+            violations.push(Violation::error(
+                "scope-enum",
+                1,
+                format!("scope `{}` is not one of {}", scope, rules.scopes.join(", ")),
+            ));
+        }
+    }
+    if commit.description.is_empty() {
+        violations.push(Violation::error("subject-empty", 1, "the subject is empty".to_string()));
+    }
+    let length = header.chars().count();
+    if length > rules.max_header_length {
+        violations.push(Violation::error(
+            "header-max-length",
+            1,
+            format!(
+                "the header has {} characters, at most {} are allowed",
+                length, rules.max_header_length
+            ),
+        ));
+    }
+    if commit.description.ends_with('.') {
+        violations.push(Violation::warning(
+            "subject-full-stop",
+            1,
+            "the subject ends with a period".to_string(),
+        ));
+    }
+    if let Some(word) = non_imperative(&commit.description) {
+        violations.push(Violation::warning(
+            "subject-imperative",
+            1,
+            format!(
+                "`{}` is not in the imperative mood, e.g. `add` instead of `added` or `adds`",
+                word
+            ),
+        ));
+    }
+
+    if lines.get(1).is_some_and(|line| !line.trim().is_empty()) {
+        violations.push(Violation::error(
+            "header-blank-line",
+            2,
+            "the header must be followed by a blank line".to_string(),
+        ));
+    }
+    for (idx, line) in lines.iter().enumerate().skip(1) {
+        // long URLs and paths cannot be wrapped
+        let length = line.chars().count();
+        if length > rules.max_body_line_length && line.trim().contains(' ') {
+            violations.push(Violation::warning(
+                "body-max-line-length",
+                idx + 1,
+                format!(
+                    "the line has {} characters, wrap at {}",
+                    length, rules.max_body_line_length
+                ),
+            ));
+        }
+    }
+    violations.extend(lint_footers(&lines));
+    violations
+}
+
+/// Checks the trailers of the last paragraph, when it holds any
+fn lint_footers(lines: &[&str]) -> Vec<Violation> {
+    let footer = Regex::new(FOOTER_PATTERN).unwrap();
+    // a token with spaces or without the space after the colon, e.g. `Signed off by:` or `Refs:#12`
+    let malformed = Regex::new(r"^[A-Za-z][A-Za-z0-9-]*:(\S|$)|^[A-Za-z][A-Za-z0-9-]* [A-Za-z0-9 -]*: ").unwrap();
+    let breaking = Regex::new(r"(?i)^breaking[ -]change\s*:").unwrap();
+
+    let start = lines
+        .iter()
+        .rposition(|line| line.trim().is_empty())
+        .map_or(lines.len(), |idx| idx + 1);
+    let paragraph = &lines[start..];
+    if !paragraph.iter().any(|line| footer.is_match(line)) {
+        return Vec::new();
+    }
+
+    let mut violations = Vec::new();
+    for (idx, line) in paragraph.iter().enumerate() {
+        let number = start + idx + 1;
+        if breaking.is_match(line) && !line.starts_with("BREAKING CHANGE: ") && !line.starts_with("BREAKING-CHANGE: ") {
+            violations.push(Violation::error(
+                "footer-breaking-change",
+                number,
+                "write breaking changes as `BREAKING CHANGE: description`".to_string(),
+            ));
+        } else if !footer.is_match(line) && malformed.is_match(line) && !line.contains("://") {
+            violations.push(Violation::error(
+                "footer-format",
+                number,
+                format!(
+                    "`{}` is not a `Token: value` trailer, tokens use `-` in place of spaces",
+                    line
+                ),
+            ));
+        }
+    }
+    violations
+}
+
+/// The first word of the subject when it looks like past tense, a gerund or third person
+fn non_imperative(subject: &str) -> Option<&str> {
+    let word = subject.split_whitespace().next()?;
+    let lower = word.to_lowercase();
+    if IMPERATIVE_EXCEPTIONS.contains(&lower.as_str()) {
+        return None;
+    }
+    let past = lower.len() > 4 && lower.ends_with("ed");
+    let gerund = lower.len() > 5 && lower.ends_with("ing");
+    (past || gerund || THIRD_PERSON.contains(&lower.as_str())).then_some(word)
+}
+
+/// Removes the comments git puts into the commit message file, and everything below the scissors line.
+///
+/// `comment` starts the comment lines, see [`crate::ai::git::git_comment_prefix`].
+pub fn strip_comments(message: &str, comment: &str) -> String {
+    let scissors = format!("{} ------------------------ >8 ------------------------", comment);
+    let mut lines = Vec::new();
+    for line in message.lines() {
+        if line.starts_with(&scissors) {
+            break;
+        }
+        if !line.starts_with(comment) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(violations: &[Violation]) -> Vec<&'static str> {
+        violations.iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let rules_cli = LintRules::new(vec!["cli".to_string()]);
+        assert!(lint(
+            "feat(cli): add lint command\n\nChecks messages.\n\nRefs: #12\nSigned-off-by: A <a@b.c>",
+            &rules_cli
+        )
+        .is_empty());
+
+        assert_eq!(rules(&lint("Update readme", &rules_cli)), ["header-format"]);
+        assert_eq!(
+            rules(&lint("feature(parser): Added arrays.\nmore text", &rules_cli)),
+            [
+                "type-enum",
+                "scope-enum",
+                "subject-full-stop",
+                "subject-imperative",
+                "header-blank-line"
+            ]
+        );
+        assert_eq!(
+            rules(&lint(&format!("fix: {}", "a".repeat(70)), &rules_cli)),
+            ["header-max-length"]
+        );
+        assert_eq!(
+            rules(&lint(&format!("fix: crash\n\n{}", "word ".repeat(25)), &rules_cli)),
+            ["body-max-line-length"]
+        );
+        assert!(lint(
+            &format!("fix: crash\n\nhttps://example.com/{}", "a".repeat(100)),
+            &rules_cli
+        )
+        .is_empty());
+
+        let violations = lint(
+            "fix: crash\n\nbody\n\nReviewed-by: B\nSigned off by: A\nRefs:#12\nbreaking change: gone",
+            &rules_cli,
+        );
+        assert_eq!(
+            rules(&violations),
+            ["footer-format", "footer-format", "footer-breaking-change"]
+        );
+        assert_eq!(violations[0].line, 6);
+
+        // prose with links in the trailer paragraph is not a malformed trailer
+        let message = "fix: crash\n\nbody\n\nSee https://example.com/issue\nhttps://example.com/pr\nRefs: #12";
+        assert!(lint(message, &rules_cli).is_empty());
+    }
+
+    #[test]
+    fn test_non_imperative() {
+        assert_eq!(non_imperative("added parser"), Some("added"));
+        assert_eq!(non_imperative("Adding parser"), Some("Adding"));
+        assert_eq!(non_imperative("fixes crash"), Some("fixes"));
+        assert_eq!(non_imperative("add parser"), None);
+        assert_eq!(non_imperative("embed fonts"), None);
+        assert_eq!(non_imperative("bring back logs"), None);
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let message = "feat: a\n\nbody\n# Please enter the commit message\n\
+            # ------------------------ >8 ------------------------\ndiff --git a/x b/x\n";
+        assert_eq!(strip_comments(message, "#"), "feat: a\n\nbody");
+
+        let message = "feat: a\n\n#1 stays\n; comment\n; ------------------------ >8 ------------------------\nx";
+        assert_eq!(strip_comments(message, ";"), "feat: a\n\n#1 stays");
+    }
+}
@@ -0,0 +1,208 @@
+use regex::Regex;
+
+pub mod lint;
+
+/// Commit types allowed by the Conventional Commits prompt, the response schema and the linter
+pub const COMMIT_TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "test", "chore", "ci", "revert", "build", "perf",
+];
+
+/// `type(scope)!: description`, the scope and the `!` are optional
+const HEADER_PATTERN: &str = r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: (?P<description>.*)$";
+/// `Token: value` or `Token #value`, tokens use `-` in place of spaces except `BREAKING CHANGE`
+const FOOTER_PATTERN: &str = r"^(?P<token>BREAKING CHANGE|[A-Za-z][A-Za-z0-9-]*)(?:: | #)(?P<value>.*)$";
+
+/// A commit message following the Conventional Commits specification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub r#type: String,
+    pub scope: Option<String>,
+    pub description: String,
+    pub body: Option<String>,
+    /// Footers in order, e.g. `("Refs", "#123")`, multi-line values are joined with newlines
+    pub footers: Vec<(String, String)>,
+    /// Marked with `!` in the header or a `BREAKING CHANGE` footer
+    pub breaking: bool,
+}
+
+impl ConventionalCommit {
+    /// Parses a commit message, `None` when the header does not follow the specification
+    pub fn parse(message: &str) -> Option<ConventionalCommit> {
+        let header = Regex::new(HEADER_PATTERN).unwrap();
+        let footer_re = Regex::new(FOOTER_PATTERN).unwrap();
+
+        let mut lines = message.trim().lines();
+        let captures = header.captures(lines.next()?.trim_end())?;
+
+        let mut body: Vec<&str> = Vec::new();
+        let mut footers: Vec<(String, String)> = Vec::new();
+        let mut previous_blank = true;
+        for line in lines {
+            let footer = footer_re
+                .captures(line)
+                .filter(|_| !footers.is_empty() || previous_blank);
+            match footer {
+                Some(footer) => footers.push((footer["token"].to_string(), footer["value"].to_string())),
+                None => match footers.last_mut() {
+                    // a footer value continues until the next footer
+                    Some((_, value)) => {
+                        value.push('\n');
+                        value.push_str(line);
+                    }
+                    None => body.push(line),
+                },
+            }
+            previous_blank = line.trim().is_empty();
+        }
+        for (_, value) in footers.iter_mut() {
+            *value = value.trim_end().to_string();
+        }
+
+        let body = body.join("\n").trim().to_string();
+        let breaking = captures.name("breaking").is_some() || footers.iter().any(|(token, _)| is_breaking_token(token));
+        Some(ConventionalCommit {
+            r#type: captures["type"].to_string(),
+            scope: captures
+                .name("scope")
+                .map(|s| s.as_str().trim().to_string())
+                .filter(|s| !s.is_empty()),
+            description: captures["description"].trim().to_string(),
+            body: Some(body).filter(|b| !b.is_empty()),
+            footers,
+            breaking,
+        })
+    }
+
+    /// Description of the breaking change: the `BREAKING CHANGE` footer, otherwise the description
+    pub fn breaking_note(&self) -> Option<&str> {
+        if !self.breaking {
+            return None;
+        }
+        let footer = self.footers.iter().find(|(token, _)| is_breaking_token(token));
+        Some(footer.map_or(self.description.as_str(), |(_, value)| value.as_str()))
+    }
+}
+
+/// Lines of the trailer block of any commit message: its last paragraph, when every line is a `Token: value`
+/// trailer or the indented continuation of one
+pub fn trailers(message: &str) -> Vec<&str> {
+    let footer = Regex::new(FOOTER_PATTERN).unwrap();
+    let lines: Vec<&str> = message.trim().lines().collect();
+    let Some(blank) = lines.iter().rposition(|line| line.trim().is_empty()) else {
+        return Vec::new();
+    };
+    let paragraph = &lines[blank + 1..];
+    let is_trailer = |(idx, line): (usize, &&str)| {
+        footer.is_match(line) || (idx > 0 && line.starts_with(char::is_whitespace))
+    };
+    if paragraph.iter().enumerate().all(is_trailer) {
+        paragraph.to_vec()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Puts trailers at the end of a message, after the trailers it already has.
+/// The model is asked to leave trailers out, the ones it repeated anyway are not duplicated.
+pub fn append_trailers(message: &str, new_trailers: &[&str]) -> String {
+    if new_trailers.is_empty() {
+        return message.to_string();
+    }
+    let lines: Vec<&str> = message.trim().lines().collect();
+    let existing = trailers(message);
+    let content = lines[..lines.len() - existing.len()].join("\n");
+    let mut block: Vec<&str> = existing.into_iter().filter(|t| !new_trailers.contains(t)).collect();
+    block.extend_from_slice(new_trailers);
+    format!("{}\n\n{}", content.trim_end(), block.join("\n"))
+}
+
+/// The `Co-authored-by` trailers of the messages, without duplicates
+pub fn co_authors(messages: &[String]) -> Vec<&str> {
+    let mut co_authors: Vec<&str> = Vec::new();
+    for message in messages {
+        for trailer in trailers(message) {
+            let is_co_author = trailer.to_lowercase().starts_with("co-authored-by:");
+            if is_co_author && !co_authors.iter().any(|c| c.eq_ignore_ascii_case(trailer)) {
+                co_authors.push(trailer);
+            }
+        }
+    }
+    co_authors
+}
+
+fn is_breaking_token(token: &str) -> bool {
+    token == "BREAKING CHANGE" || token == "BREAKING-CHANGE"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let commit = ConventionalCommit::parse(
+            "feat(parser): support arrays\n\nArrays of tables are parsed too.\n\nRefs: #12\nBREAKING CHANGE: tables\n  are now arrays\n",
+        )
+        .unwrap();
+        assert_eq!(commit.r#type, "feat");
+        assert_eq!(commit.scope.as_deref(), Some("parser"));
+        assert_eq!(commit.description, "support arrays");
+        assert_eq!(commit.body.as_deref(), Some("Arrays of tables are parsed too."));
+        assert_eq!(
+            commit.footers,
+            vec![
+                ("Refs".to_string(), "#12".to_string()),
+                ("BREAKING CHANGE".to_string(), "tables\n  are now arrays".to_string())
+            ]
+        );
+        assert!(commit.breaking);
+        assert_eq!(commit.breaking_note(), Some("tables\n  are now arrays"));
+
+        let commit = ConventionalCommit::parse("fix!: reject empty vendor\n\nNote: this is body text").unwrap();
+        assert_eq!(commit.scope, None);
+        assert_eq!(commit.body, None);
+        assert_eq!(commit.footers, vec![("Note".to_string(), "this is body text".to_string())]);
+        assert_eq!(commit.breaking_note(), Some("reject empty vendor"));
+
+        let commit = ConventionalCommit::parse("docs: usage\n\nSee the README: it explains more").unwrap();
+        assert_eq!(commit.body.as_deref(), Some("See the README: it explains more"));
+        assert!(commit.footers.is_empty());
+        assert_eq!(commit.breaking_note(), None);
+
+        assert_eq!(ConventionalCommit::parse("Merge branch 'main'"), None);
+        assert_eq!(ConventionalCommit::parse("feat(cli):missing space"), None);
+    }
+
+    #[test]
+    fn test_append_trailers() {
+        let trailers = ["Signed-off-by: A <a@b.c>", "Refs: #12"];
+        assert_eq!(
+            append_trailers("fix(cli): reject empty vendor\n\nThe vendor was optional.", &trailers),
+            "fix(cli): reject empty vendor\n\nThe vendor was optional.\n\nSigned-off-by: A <a@b.c>\nRefs: #12"
+        );
+        assert_eq!(
+            append_trailers("fix: crash\n\nBREAKING CHANGE: x\nRefs: #12", &trailers),
+            "fix: crash\n\nBREAKING CHANGE: x\nSigned-off-by: A <a@b.c>\nRefs: #12"
+        );
+        assert_eq!(append_trailers("fix: crash", &[]), "fix: crash");
+    }
+
+    #[test]
+    fn test_co_authors() {
+        let messages = [
+            "wip\n\nCo-authored-by: A <a@b.c>".to_string(),
+            "feat: x\n\nSigned-off-by: B <b@c.d>\nco-authored-by: a <A@B.C>\nCo-authored-by: C <c@d.e>".to_string(),
+        ];
+        assert_eq!(co_authors(&messages), vec!["Co-authored-by: A <a@b.c>", "Co-authored-by: C <c@d.e>"]);
+    }
+
+    #[test]
+    fn test_trailers() {
+        assert_eq!(
+            trailers("Fixed stuff\n\nmore\n\nSigned-off-by: A <a@b.c>\nCo-authored-by: B\n  <b@c.d>\n"),
+            vec!["Signed-off-by: A <a@b.c>", "Co-authored-by: B", "  <b@c.d>"]
+        );
+        assert!(trailers("fix: crash\n\nThe parser crashed on: empty input").is_empty());
+        assert!(trailers("Refs: #1").is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::ai::git::{git_comment_prefix, git_interpret_trailers, git_stage_diff, git_stage_filenames};
+use crate::args::CommandArgs;
+use crate::conventional::lint::{self, LintRules, Severity};
+use crate::{ai, conventional, llm};
+
+/// Marker identifying hooks written by GitBuddy, other hooks are never overwritten or removed
+const HOOK_MARKER: &str = "# Installed by gitbuddy";
+
+pub const PREPARE_COMMIT_MSG: &str = "prepare-commit-msg";
+pub const COMMIT_MSG: &str = "commit-msg";
+
+/// Messages written by git itself, which are never checked
+const GENERATED_PREFIXES: [&str; 5] = ["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "];
+
+/// Installs a git hook running `gitbuddy hook <name> [options]` in the current repository
+pub fn install(name: &str, options: &[&str]) -> Result<()> {
+    let path = hook_path(name)?;
+    if path.exists() && !is_gitbuddy_hook(&path) {
+        return Err(anyhow!(
+            "{} already exists and was not installed by gitbuddy, remove it first",
+            path.display()
+        ));
+    }
+
+    // prefer the absolute path, IDEs often run hooks without the user's PATH
+    let program = std::env::current_exe()
+        .map(|p| p.display().to_string())
+        .unwrap_or("gitbuddy".to_string());
+    let options: String = options.iter().map(|o| format!(" {}", o)).collect();
+    let script = format!(
+        "#!/bin/sh\n{HOOK_MARKER}, remove with `gitbuddy hook uninstall {name}`\nexec \"{program}\" hook {name}{options} \"$@\"\n"
+    );
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    println!("{} Installed {}", "✨".bright_green(), path.display().to_string().bright_green());
+    Ok(())
+}
+
+/// Removes a git hook previously installed by [`install`]
+pub fn uninstall(name: &str) -> Result<()> {
+    let path = hook_path(name)?;
+    if !path.exists() {
+        println!("{} is not installed", name);
+        return Ok(());
+    }
+    if !is_gitbuddy_hook(&path) {
+        return Err(anyhow!("{} was not installed by gitbuddy, leaving it untouched", path.display()));
+    }
+
+    fs::remove_file(&path)?;
+    println!("{} Removed {}", "✨".bright_green(), path.display().to_string().bright_green());
+    Ok(())
+}
+
+/// Runs the `prepare-commit-msg` hook: writes a generated message into the commit message file.
+///
+/// After `git merge --squash`, the message replaces the concatenated messages of the squashed commits, keeping their
+/// `Co-authored-by` trailers. They are dropped rather than commented out: `git commit --no-edit` only cleans up
+/// whitespace, comments and scissors lines would be committed.
+///
+/// # Arguments
+/// * `file` - The commit message file, usually `.git/COMMIT_EDITMSG`
+/// * `source` - The source of the commit message given by git: `message`, `template`, `merge`, `squash` or `commit`
+/// * `args` - The command arguments used to generate the message
+pub fn prepare_commit_msg(file: &str, source: Option<&str>, args: &CommandArgs) -> Result<()> {
+    // only plain `git commit` and squashes get a draft, merges, amends and messages given with -m/-F/-t are kept
+    if !matches!(source, None | Some("squash")) {
+        return Ok(());
+    }
+
+    let filenames = git_stage_filenames()?;
+    if filenames.is_empty() {
+        return Ok(());
+    }
+
+    let original = fs::read_to_string(file).unwrap_or_default();
+    let diff_content = git_stage_diff(&args.ignore)?;
+    let style = ai::commit_style(args, &filenames);
+    let content = if source.is_some() {
+        let commits = squashed_messages(&original);
+        let llm_result = llm::llm_squash_request(&diff_content, &commits, &style, args)?;
+        let message = first_message(&llm_result)?;
+        let message = conventional::append_trailers(message, &conventional::co_authors(&commits));
+        format!("{}\n", git_interpret_trailers(&message, &args.trailers)?)
+    } else {
+        let llm_result = llm::llm_request(&diff_content, args.prompt, &style, args)?;
+        let message = git_interpret_trailers(first_message(&llm_result)?, &args.trailers)?;
+        format!("{}\n{}", message, original)
+    };
+    fs::write(file, content)?;
+    Ok(())
+}
+
+fn first_message(llm_result: &llm::LLMResult) -> Result<&String> {
+    llm_result
+        .commit_messages
+        .first()
+        .ok_or_else(|| anyhow!("no commit message generated"))
+}
+
+/// The messages of the commits listed by `git merge --squash`, oldest first.
+///
+/// git lists them newest first as `commit <id>`, `Author:` and `Date:` headers followed by the message indented
+/// by four spaces.
+fn squashed_messages(squash_msg: &str) -> Vec<String> {
+    let mut messages: Vec<String> = Vec::new();
+    for line in squash_msg.lines() {
+        if line.starts_with("commit ") {
+            messages.push(String::new());
+        } else if let (Some(message), Some(text)) = (messages.last_mut(), line.strip_prefix("    ")) {
+            message.push_str(text);
+            message.push('\n');
+        } else if let (Some(message), true) = (messages.last_mut(), line.is_empty()) {
+            if !message.is_empty() {
+                message.push('\n');
+            }
+        }
+    }
+    messages.reverse();
+    messages.into_iter().map(|m| m.trim().to_string()).filter(|m| !m.is_empty()).collect()
+}
+
+/// Runs the `commit-msg` hook: rejects messages breaking the conventions, explaining why.
+///
+/// With `fix`, the model rewrites a rejected message instead, keeping its trailers, and the commit goes on when the
+/// rewrite passes the linter.
+///
+/// # Arguments
+/// * `file` - The commit message file, usually `.git/COMMIT_EDITMSG`
+/// * `fix` - Whether to rewrite rejected messages
+/// * `args` - The command arguments used to rewrite the message
+pub fn commit_msg(file: &str, fix: bool, args: &CommandArgs) -> Result<()> {
+    let message = lint::strip_comments(&fs::read_to_string(file)?, &git_comment_prefix());
+    // git aborts empty messages by itself
+    if message.is_empty() || GENERATED_PREFIXES.iter().any(|prefix| message.starts_with(prefix)) {
+        return Ok(());
+    }
+
+    let rules = LintRules::new(args.scopes.clone());
+    let violations = lint::lint(&message, &rules);
+    for violation in &violations {
+        eprintln!("  {}", violation);
+    }
+    let errors: Vec<String> = violations
+        .iter()
+        .filter(|v| v.severity == Severity::Error)
+        .map(|v| format!("{} ({})", v.message, v.rule))
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    if !fix {
+        return Err(anyhow!(
+            "the commit message does not follow the Conventional Commits conventions, it is kept in {}",
+            file
+        ));
+    }
+
+    let trailers = conventional::trailers(&message);
+    let lines: Vec<&str> = message.lines().collect();
+    let content = lines[..lines.len() - trailers.len()].join("\n");
+    let style = ai::commit_style(args, &[]);
+    let llm_result = llm::llm_rewrite_message(content.trim(), &errors, &style, args)?;
+    let rewritten = llm_result
+        .commit_messages
+        .first()
+        .ok_or_else(|| anyhow!("no commit message generated"))?;
+    let rewritten = conventional::append_trailers(rewritten, &trailers);
+
+    let remaining: Vec<_> = lint::lint(&rewritten, &rules)
+        .into_iter()
+        .filter(|v| v.severity == Severity::Error)
+        .collect();
+    if !remaining.is_empty() {
+        for violation in &remaining {
+            eprintln!("  {}", violation);
+        }
+        return Err(anyhow!("the rewritten commit message still breaks the conventions"));
+    }
+
+    fs::write(file, format!("{}\n", rewritten))?;
+    eprintln!("{}\n{}", "Rewrote the commit message:".bright_green(), rewritten.cyan());
+    Ok(())
+}
+
+/// Returns the path of the hook, honouring `core.hooksPath`
+fn hook_path(name: &str) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", &format!("hooks/{}", name)])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("Not git directory"));
+    }
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+fn is_gitbuddy_hook(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|content| content.contains(HOOK_MARKER))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_squashed_messages() {
+        let squash_msg = "Squashed commit of the following:\n\n\
+            commit 2222\nAuthor: B <b@c.d>\nDate:   Sun Oct 18 10:00:00 2026 +0000\n\n    fix typo\n\n    Co-authored-by: C <c@d.e>\n\n\
+            commit 1111\nAuthor: A <a@b.c>\nDate:   Sun Oct 18 09:00:00 2026 +0000\n\n    wip\n";
+        assert_eq!(
+            squashed_messages(squash_msg),
+            vec!["wip".to_string(), "fix typo\n\nCo-authored-by: C <c@d.e>".to_string()]
+        );
+    }
+}
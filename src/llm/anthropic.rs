@@ -0,0 +1,282 @@
+use crate::config::{HttpConfig, ModelConfig, ModelParameters};
+use crate::llm::provider::{ChatChunk, ChatStream, Client, TokenUsage};
+use crate::llm::{llm, retry};
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Client for the Anthropic Messages API (`/v1/messages`)
+pub struct AnthropicClient {
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    client: reqwest::blocking::Client,
+    http: HttpConfig,
+}
+
+impl AnthropicClient {
+    pub fn new_from_config(conf: &ModelConfig) -> AnthropicClient {
+        AnthropicClient {
+            base_url: conf.base_url.clone(),
+            model: conf.model.clone(),
+            api_key: conf.api_key.clone(),
+            client: retry::http_client(&conf.http),
+            http: conf.http,
+        }
+    }
+}
+
+impl Client for AnthropicClient {
+    fn endpoint(&self) -> &str {
+        &self.base_url
+    }
+
+    fn stream_chat(
+        &self,
+        messages: Vec<llm::Message>,
+        option: ModelParameters,
+        _schema: Option<&Value>,
+    ) -> anyhow::Result<ChatStream> {
+        // the Messages API takes the system prompt as a top-level field instead of a message
+        let (system, messages): (Vec<_>, Vec<_>) = messages.into_iter().partition(|m| m.role == "system");
+        let system = system.into_iter().map(|m| m.content).collect::<Vec<_>>().join("\n\n");
+
+        // the Messages API has no json_schema response format, the prompt alone describes the output
+        // top_p is left out on purpose, recent models reject requests setting both temperature and top_p
+        let payload = &json!({
+            "model": self.model,
+            "system": system,
+            "messages": messages,
+            "max_tokens": option.max_tokens,
+            "temperature": option.temperature,
+            "top_k": option.top_k,
+            "stream": true,
+        });
+
+        let (response, retries) = retry::send(&self.http, || {
+            let mut builder = self.client.post(format!("{}/messages", self.base_url.trim_end_matches('/')));
+            if let Some(key) = &self.api_key {
+                builder = builder.header("x-api-key", key);
+            }
+            builder
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .header("Accept", "text/event-stream")
+                .header("Content-Type", "application/json")
+                .json(payload)
+        })?;
+
+        let reader = BufReader::new(response);
+        let stream: ChatStream = Box::new(
+            reader
+                .lines()
+                .filter_map(|l| match l {
+                    Ok(s) => s.strip_prefix("data:").map(|s| Ok(s.trim().to_string())),
+                    Err(e) => Some(Err(retry::stream_error(e))),
+                })
+                .filter_map(|s| {
+                    let s = match s {
+                        Ok(s) => s,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let event: AnthropicStreamEvent = match serde_json::from_str(&s) {
+                        Ok(event) => event,
+                        Err(e) => return Some(Err(anyhow!("invalid stream event: {e}, data: {s}"))),
+                    };
+                    event.into_chunk().transpose()
+                }),
+        );
+        Ok(retry::count_retries(retries, stream))
+    }
+}
+
+/// Server-sent events of a streamed Messages API response
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    MessageStart { message: AnthropicMessage },
+    ContentBlockDelta { delta: AnthropicDelta },
+    MessageDelta { usage: AnthropicUsage },
+    Error { error: AnthropicError },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicMessage {
+    usage: AnthropicUsage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicDelta {
+    TextDelta {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: i64,
+    #[serde(default)]
+    output_tokens: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicError {
+    r#type: String,
+    message: String,
+}
+
+impl AnthropicStreamEvent {
+    fn into_chunk(self) -> anyhow::Result<Option<ChatChunk>> {
+        match self {
+            // input tokens are reported once when the message starts
+            AnthropicStreamEvent::MessageStart { message } => Ok(Some(ChatChunk {
+                content: None,
+                usage: Some(TokenUsage {
+                    completion_tokens: 0,
+                    prompt_tokens: message.usage.input_tokens,
+                    total_tokens: message.usage.input_tokens,
+                    retries: 0,
+                }),
+            })),
+            AnthropicStreamEvent::ContentBlockDelta {
+                delta: AnthropicDelta::TextDelta { text },
+            } => Ok(Some(ChatChunk {
+                content: Some(text),
+                usage: None,
+            })),
+            // output tokens in message_delta are cumulative for the whole message
+            AnthropicStreamEvent::MessageDelta { usage } => Ok(Some(ChatChunk {
+                content: None,
+                usage: Some(TokenUsage {
+                    completion_tokens: usage.output_tokens,
+                    prompt_tokens: 0,
+                    total_tokens: usage.output_tokens,
+                    retries: 0,
+                }),
+            })),
+            AnthropicStreamEvent::Error { error } => Err(anyhow!("{}: {}", error.r#type, error.message)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::llm::mock;
+    use crate::llm::provider::ProviderKind;
+
+    const STREAM: &str = r#"event: message_start
+data: {"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","content":[],"model":"claude-3-5-haiku-latest","usage":{"input_tokens":25,"output_tokens":1}}}
+
+event: content_block_start
+data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}
+
+event: ping
+data: {"type": "ping"}
+
+event: content_block_delta
+data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"[{\"type\": \"feat\","}}
+
+event: content_block_delta
+data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" \"subject\": \"add parser\"}]"}}
+
+event: content_block_stop
+data: {"type":"content_block_stop","index":0}
+
+event: message_delta
+data: {"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":15}}
+
+event: message_stop
+data: {"type":"message_stop"}
+
+"#;
+
+    fn model_config(base_url: String) -> ModelConfig {
+        ModelConfig {
+            api_key: Some("sk-ant-test".to_string()),
+            model: "claude-3-5-haiku-latest".to_string(),
+            base_url: format!("{base_url}/v1"),
+            kind: ProviderKind::Anthropic,
+            structured_output: false,
+            http: HttpConfig::default(),
+        }
+    }
+
+    fn model_parameters() -> ModelParameters {
+        ModelParameters {
+            temperature: 0.1,
+            top_p: 0.75,
+            top_k: 5,
+            max_tokens: 1024,
+        }
+    }
+
+    #[test]
+    fn test_stream_chat() {
+        let (url, server) = mock::serve_once(200, "text/event-stream", STREAM);
+        let client = AnthropicClient::new_from_config(&model_config(url));
+
+        let messages = vec![
+            llm::Message::new_system("you are a commit message generator".to_string()),
+            llm::Message::new_user("diff".to_string()),
+        ];
+        let chunks = client
+            .stream_chat(messages, model_parameters(), None)
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        let content: String = chunks.iter().filter_map(|c| c.content.clone()).collect();
+        assert_eq!(content, r#"[{"type": "feat", "subject": "add parser"}]"#);
+        let (prompt, completion) = chunks
+            .iter()
+            .filter_map(|c| c.usage)
+            .fold((0, 0), |(p, c), u| (p + u.prompt_tokens, c + u.completion_tokens));
+        assert_eq!((prompt, completion), (25, 15));
+
+        let request = server.join().unwrap();
+        assert_eq!(request.request_line, "POST /v1/messages HTTP/1.1");
+        assert_eq!(request.header("x-api-key"), Some("sk-ant-test"));
+        assert_eq!(request.header("anthropic-version"), Some(ANTHROPIC_VERSION));
+        let body = request.json();
+        assert_eq!(body["system"], "you are a commit message generator");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(body["max_tokens"], 1024);
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn test_stream_chat_error_event() {
+        let stream = "event: error\n\
+            data: {\"type\": \"error\", \"error\": {\"type\": \"overloaded_error\", \"message\": \"Overloaded\"}}\n\n";
+        let (url, server) = mock::serve_once(200, "text/event-stream", stream);
+        let client = AnthropicClient::new_from_config(&model_config(url));
+
+        let result = client
+            .stream_chat(vec![llm::Message::new_user("diff".to_string())], model_parameters(), None)
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>();
+        assert!(result.unwrap_err().to_string().contains("overloaded_error"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_stream_chat_http_error() {
+        let (url, server) = mock::serve_once(401, "application/json", r#"{"type":"error"}"#);
+        let client = AnthropicClient::new_from_config(&model_config(url));
+
+        let result = client.stream_chat(vec![llm::Message::new_user("diff".to_string())], model_parameters(), None);
+        assert!(result.is_err());
+        server.join().unwrap();
+    }
+}
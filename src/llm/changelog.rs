@@ -0,0 +1,33 @@
+use crate::config::{ModelConfig, ModelParameters};
+use crate::llm::git_commit::stream_chat_response;
+use crate::llm::llm;
+use crate::llm::provider::{new_client, TokenUsage};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+/// Ask the model to rewrite the entries of a generated changelog into user-facing prose
+pub(crate) fn polish_changelog(
+    changelog: &str,
+    model_config: &ModelConfig,
+    option: ModelParameters,
+    prompt: String,
+) -> Result<(String, TokenUsage)> {
+    let client = new_client(model_config);
+    let messages = vec![
+        llm::Message::new_system(prompt),
+        llm::Message::new_user(format!("Rewrite this changelog:\n\n{changelog}")),
+    ];
+    let (output, usage) = stream_chat_response(option, client.as_ref(), messages, None, true)?;
+
+    let re = Regex::new(r"(?s)<think>.*?</think>").unwrap();
+    let output = re.replace_all(&output, "");
+    let mut output = output.trim();
+    if let Some(inner) = output.strip_prefix("```") {
+        let inner = inner.split_once('\n').map_or("", |(_, rest)| rest);
+        output = inner.trim_end().strip_suffix("```").unwrap_or(inner).trim();
+    }
+    if output.is_empty() {
+        return Err(anyhow!("no changelog generated"));
+    }
+    Ok((format!("{}\n", output), usage))
+}
@@ -0,0 +1,170 @@
+use crate::conventional::COMMIT_TYPES;
+use crate::llm::theme;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+// the JSON schema of the output is derived from these structs, their doc comments are read by the model
+/// Conventional commit message
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CommitMessage {
+    /// Type of current commit
+    #[schemars(schema_with = "commit_type_schema")]
+    pub r#type: String,
+    /// Affected component, e.g. auth/view
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Short summary of the change, must be in imperative mood and under 80 characters, e.g. add oauth2
+    /// authentication flow
+    pub subject: String,
+    /// Detailed description of the change
+    pub body: Option<String>,
+    /// Additional information, e.g., breaking changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+    /// Staged file paths belonging to this commit, only present when splitting a change-set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<String>>,
+}
+
+// an object wrapping the array, `json_schema` response formats must be objects
+/// Generate conventional commit messages
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CommitMessages {
+    /// Commit message options
+    pub commits: Vec<CommitMessage>,
+}
+
+fn commit_type_schema(_: &mut SchemaGenerator) -> Schema {
+    let schema = json!({"type": "string", "description": "Type of current commit", "enum": COMMIT_TYPES});
+    serde_json::from_value(schema).expect("valid schema")
+}
+
+impl CommitMessage {
+    /// Formats the message as a Conventional Commit, with the optional issue reference appended to the header
+    pub fn format(&self, reference: Option<&String>) -> String {
+        let mut commit = String::new();
+
+        // 构建提交消息头
+        match self.scope.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            Some(scope) => commit.push_str(&format!("{}({}): {}", self.r#type, scope, self.subject.trim())),
+            None => commit.push_str(&format!("{}: {}", self.r#type, self.subject.trim())),
+        }
+
+        // 添加 issue 引用
+        if let Some(r) = reference {
+            commit.push(' ');
+            commit.push_str(r.as_str());
+        }
+
+        // 添加可选的消息体
+        if let Some(body) = self.body.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            commit.push_str("\n\n");
+            commit.push_str(&theme::wrap_text(body, 100));
+        }
+
+        // 添加可选的页脚
+        if let Some(footer) = self.footer.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            commit.push_str("\n\n");
+            commit.push_str(&theme::wrap_text(footer, 100));
+        }
+
+        commit
+    }
+
+    /// JSON schema of the output embedded in the prompt
+    ///
+    /// # Arguments
+    /// * `max_items` - Number of commit messages asked for
+    /// * `files` - Whether the commits list their files, used when splitting a change-set
+    pub fn prompt_schema(max_items: Option<u8>, files: bool) -> Value {
+        CommitMessages::schema(max_items, files, false)
+    }
+
+    /// JSON schema sent as the `json_schema` response format, the same shape as [`CommitMessage::prompt_schema`]
+    /// in strict mode: every property is required and optional ones are nullable
+    pub fn response_schema(max_items: Option<u8>, files: bool) -> Value {
+        CommitMessages::schema(max_items, files, true)
+    }
+}
+
+impl CommitMessages {
+    fn schema(max_items: Option<u8>, files: bool, strict: bool) -> Value {
+        let settings = SchemaSettings::draft07().with(|s| {
+            s.inline_subschemas = true;
+            s.meta_schema = None;
+        });
+        let root = settings.into_generator().into_root_schema_for::<CommitMessages>();
+        let mut schema = serde_json::to_value(root).expect("schema serializes");
+
+        let commits = &mut schema["properties"]["commits"];
+        commits["minItems"] = json!(1);
+        if let Some(max_items) = max_items {
+            commits["maxItems"] = json!(max_items);
+        }
+        let item = &mut commits["items"];
+        if !files {
+            item["properties"].as_object_mut().expect("properties").remove("files");
+        }
+        let required: Vec<String> = item["properties"]
+            .as_object()
+            .expect("properties")
+            .keys()
+            .filter(|k| strict || ["type", "subject", "files"].contains(&k.as_str()))
+            .cloned()
+            .collect();
+        item["required"] = json!(required);
+        if strict {
+            item["additionalProperties"] = json!(false);
+            schema["additionalProperties"] = json!(false);
+        }
+        schema
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_schema_matches_struct() {
+        let message = CommitMessage {
+            r#type: "feat".to_string(),
+            scope: Some("parser".to_string()),
+            subject: "add parser".to_string(),
+            body: Some("body".to_string()),
+            footer: Some("footer".to_string()),
+            files: Some(vec!["src/parser.rs".to_string()]),
+        };
+        let fields = serde_json::to_value(&message).unwrap();
+        let fields: Vec<&String> = fields.as_object().unwrap().keys().collect();
+
+        let schema = CommitMessage::response_schema(Some(3), true);
+        let item = &schema["properties"]["commits"]["items"];
+        let properties: Vec<&String> = item["properties"].as_object().unwrap().keys().collect();
+        assert_eq!(fields, properties);
+        assert_eq!(item["required"].as_array().unwrap().len(), fields.len());
+        assert_eq!(item["additionalProperties"], false);
+        assert_eq!(item["properties"]["type"]["enum"], json!(COMMIT_TYPES));
+        assert_eq!(item["properties"]["body"]["type"], json!(["string", "null"]));
+        assert_eq!(schema["required"], json!(["commits"]));
+        assert_eq!(schema["properties"]["commits"]["maxItems"], 3);
+
+        // the prompt describes the same object
+        let schema = CommitMessage::prompt_schema(Some(3), false);
+        let item = &schema["properties"]["commits"]["items"];
+        assert_eq!(item["required"], json!(["subject", "type"]));
+        assert!(item["properties"].get("files").is_none());
+        assert_eq!(schema["properties"]["commits"]["maxItems"], 3);
+        assert_eq!(schema["properties"]["commits"]["minItems"], 1);
+    }
+
+    #[test]
+    fn test_strict_response_round_trip() {
+        let output = r#"{"commits": [{"type": "fix", "scope": null, "subject": "handle empty diff", "body": null, "footer": null}]}"#;
+        let messages: CommitMessages = serde_json::from_str(output).unwrap();
+        assert_eq!(messages.commits[0].format(None), "fix: handle empty diff");
+    }
+}
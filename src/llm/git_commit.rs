@@ -1,80 +1,307 @@
-use crate::args::CommandArgs;
-use crate::config::{ModelConfig, ModelParameters};
-use crate::llm::openai::{OpenAIClient, OpenAIResponseUsage};
+use crate::args::{CommandArgs, Output};
+use crate::config::{DiffConfig, ModelConfig, ModelParameters};
+use crate::llm::commit::{CommitMessage, CommitMessages};
+use crate::llm::provider::{new_client, Client, TokenUsage};
+use crate::llm::summarize::{estimate_tokens, split_diff, summarize_diff, DiffStrategy};
 use crate::llm::{llm, theme, LLMResult};
 use anyhow::{Error, Result};
 use colored::Colorize;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::io;
-use std::io::{BufRead, Write};
+use std::io::Write;
 
 pub(crate) fn generate_git_commit_messages(
     diff_content: &str,
     model_config: &ModelConfig,
     option: ModelParameters,
+    diff: &DiffConfig,
     args: &CommandArgs,
     prompt: String,
 ) -> Result<LLMResult, anyhow::Error> {
-    let client = OpenAIClient::new_from_config(model_config, None);
-    print_configuration(&model_config.model, diff_content, &option, &client.base_url, args);
+    let client = new_client(model_config);
+    let (strategy, chunks) = plan_diff(diff_content, diff);
+    print_configuration(model_config, diff_content, &option, client.endpoint(), &strategy, args);
 
-    let messages = git_commit_prompt(diff_content, args.hint.as_ref(), prompt);
+    let (changes, usage) = describe_changes(client.as_ref(), diff_content, &chunks, &strategy, &option)?;
+    let messages = git_commit_prompt(&changes, args.hint.as_ref(), prompt);
+    // a single rewrite is asked for, whatever the number of commit options
+    let schema = model_config
+        .structured_output
+        .then(|| CommitMessage::response_schema(Some(1), false));
 
-    let (output, usage) = stream_chat_response(option, client, messages)?;
+    let mut result = complete_git_commit_messages(client.as_ref(), messages, option, schema, args)?;
+    result.add_usage(usage);
+    Ok(result)
+}
+
+/// Ask the model for the message of a squash commit, from the messages of the squashed commits and their
+/// combined diff.
+pub(crate) fn generate_squash_commit_messages(
+    diff_content: &str,
+    commits: &[String],
+    model_config: &ModelConfig,
+    option: ModelParameters,
+    diff: &DiffConfig,
+    args: &CommandArgs,
+    prompt: String,
+) -> Result<LLMResult, anyhow::Error> {
+    let client = new_client(model_config);
+    let (strategy, chunks) = plan_diff(diff_content, diff);
+    print_configuration(model_config, diff_content, &option, client.endpoint(), &strategy, args);
+
+    let (changes, usage) = describe_changes(client.as_ref(), diff_content, &chunks, &strategy, &option)?;
+    let messages = git_squash_prompt(&changes, commits, args.hint.as_ref(), prompt);
+    // a single rewrite is asked for, whatever the number of commit options
+    let schema = model_config
+        .structured_output
+        .then(|| CommitMessage::response_schema(Some(1), false));
+
+    let mut result = complete_git_commit_messages(client.as_ref(), messages, option, schema, args)?;
+    result.add_usage(usage);
+    Ok(result)
+}
+
+/// Ask the model to group the staged files into several logical commits, with a message for each group.
+pub(crate) fn generate_git_commit_groups(
+    diff_content: &str,
+    filenames: &[String],
+    model_config: &ModelConfig,
+    option: ModelParameters,
+    diff: &DiffConfig,
+    args: &CommandArgs,
+    prompt: String,
+) -> Result<LLMResult, anyhow::Error> {
+    let client = new_client(model_config);
+    let (strategy, chunks) = plan_diff(diff_content, diff);
+    print_configuration(model_config, diff_content, &option, client.endpoint(), &strategy, args);
+
+    let (changes, usage) = describe_changes(client.as_ref(), diff_content, &chunks, &strategy, &option)?;
+    let messages = git_split_prompt(&changes, filenames, args.hint.as_ref(), prompt);
+    let schema = model_config
+        .structured_output
+        .then(|| CommitMessage::response_schema(None, true));
+
+    let mut result = complete_git_commit_messages(client.as_ref(), messages, option, schema, args)?;
+    result.add_usage(usage);
+    Ok(result)
+}
+
+/// Continue a previous commit message conversation with a follow-up user turn.
+pub(crate) fn regenerate_git_commit_messages(
+    mut messages: Vec<llm::Message>,
+    feedback: Option<&str>,
+    schema: Option<Value>,
+    model_config: &ModelConfig,
+    option: ModelParameters,
+    args: &CommandArgs,
+) -> Result<LLMResult, anyhow::Error> {
+    let client = new_client(model_config);
+    messages.push(git_commit_feedback(feedback));
+    complete_git_commit_messages(client.as_ref(), messages, option, schema, args)
+}
+
+/// Ask the model to rewrite a hand-written commit message into a compliant one, keeping its intent.
+///
+/// # Arguments
+/// * `message` - The commit message without its trailers
+/// * `problems` - The broken rules of the message, explained to the model
+pub(crate) fn rewrite_git_commit_message(
+    message: &str,
+    problems: &[String],
+    model_config: &ModelConfig,
+    option: ModelParameters,
+    args: &CommandArgs,
+    prompt: String,
+) -> Result<LLMResult, anyhow::Error> {
+    let client = new_client(model_config);
+    let problems = problems.iter().map(|p| format!("- {p}")).collect::<Vec<_>>().join("\n");
+    let messages = git_commit_conversation(
+        prompt,
+        format!(
+            "Rewrite this hand-written commit message so it follows the Conventional Commits specification:\n\
+            ```text\n{message}\n```\n\n\
+            It breaks these rules:\n{problems}\n\n\
+            Keep the intent and the details of the message, only fix its form. Leave out trailers such as \
+            Signed-off-by, they are kept as they are.\n\
+            Output should be only json format, and without comments and explanations.\n"
+        ),
+        args.hint.as_ref(),
+    );
+    // a single rewrite is asked for, whatever the number of commit options
+    let schema = model_config
+        .structured_output
+        .then(|| CommitMessage::response_schema(Some(1), false));
+    complete_git_commit_messages(client.as_ref(), messages, option, schema, args)
+}
+
+/// Decide whether the diff fits in a single request, or must be summarised chunk by chunk.
+///
+/// Each chunk costs a request, so a diff of more than `max_chunks` chunks is truncated to the budget instead.
+pub(super) fn plan_diff(diff_content: &str, diff: &DiffConfig) -> (DiffStrategy, Vec<String>) {
+    let mut chunks = if estimate_tokens(diff_content) > diff.max_tokens {
+        split_diff(diff_content, diff.max_tokens)
+    } else {
+        vec![]
+    };
+    if chunks.len() > diff.max_chunks {
+        chunks.truncate(1);
+    }
+    let strategy = match chunks.len() {
+        0 => DiffStrategy::Single,
+        1 => DiffStrategy::Truncated,
+        n => DiffStrategy::MapReduce { chunks: n },
+    };
+    (strategy, chunks)
+}
+
+/// Describe the staged changes for the user message, either as the diff itself or as summaries of its chunks
+pub(super) fn describe_changes(
+    client: &dyn Client,
+    diff_content: &str,
+    chunks: &[String],
+    strategy: &DiffStrategy,
+    option: &ModelParameters,
+) -> Result<(String, TokenUsage), anyhow::Error> {
+    match strategy {
+        DiffStrategy::Single => Ok((format!("```diff\n{diff_content}\n```"), TokenUsage::default())),
+        DiffStrategy::Truncated => {
+            let diff = chunks.first().map_or(diff_content, |c| c.as_str());
+            Ok((format!("```diff\n{diff}\n```"), TokenUsage::default()))
+        }
+        DiffStrategy::MapReduce { .. } => {
+            let (summaries, usage) = summarize_diff(client, chunks, option)?;
+            Ok((
+                format!(
+                    "The diff is too large to be sent at once, \
+                    these are the summaries of each part of it:\n{summaries}"
+                ),
+                usage,
+            ))
+        }
+    }
+}
+
+fn complete_git_commit_messages(
+    client: &dyn Client,
+    messages: Vec<llm::Message>,
+    option: ModelParameters,
+    schema: Option<Value>,
+    args: &CommandArgs,
+) -> Result<LLMResult, anyhow::Error> {
+    let echo = args.output == Output::Text;
+    let (output, usage) = stream_chat_response(option, client, messages.clone(), schema.as_ref(), echo)?;
 
     let re = Regex::new(r"(?s)<think>.*?</think>")
         .map_err(|e| format!("invalid regex, err: {e}"))
         .unwrap();
-    let message = re.replace_all(&output.trim(), "").trim().to_string();
-    let messages = process_llm_response(message.clone(), args.reference.as_ref())?;
+    let message = re.replace_all(output.trim(), "").trim().to_string();
+    let commits = parse_commits(&message, schema.is_some())?;
 
     Ok(LLMResult {
         completion_tokens: usage.completion_tokens,
         prompt_tokens: usage.prompt_tokens,
         total_tokens: usage.total_tokens,
+        retries: usage.retries,
         commit_message: message,
-        commit_messages: messages,
+        commit_messages: commits.iter().map(|c| c.format(args.reference.as_ref())).collect(),
+        commit_files: commits.iter().map(|c| c.files.clone().unwrap_or_default()).collect(),
+        candidates: commits,
+        messages,
+        response_schema: schema,
     })
 }
 
-fn stream_chat_response(
+/// Streams the completion of the conversation, echoing it to the terminal unless `echo` is false
+pub(super) fn stream_chat_response(
     option: ModelParameters,
-    client: OpenAIClient,
+    client: &dyn Client,
     messages: Vec<llm::Message>,
-) -> Result<(String, OpenAIResponseUsage), Error> {
+    schema: Option<&Value>,
+    echo: bool,
+) -> Result<(String, TokenUsage), Error> {
     let mut output = String::new();
-    let mut usage = OpenAIResponseUsage::default();
+    let mut usage = TokenUsage::default();
 
     let (start_separator, end_separator) = theme::get_stream_separator(3); // 使用方案2，可以改为1或3尝试其他效果
-    println!("{}", start_separator);
-    for (data, _line) in client.stream_chat(messages, option)? {
-        for choice in data.choices {
-            if let Some(content) = choice.delta.content {
+    if echo {
+        println!("{}", start_separator);
+    }
+    for chunk in client.stream_chat(messages, option, schema)? {
+        let data = chunk?;
+        if let Some(content) = data.content {
+            if echo {
                 print!("{}", content.cyan());
                 io::stdout().flush()?; // flush to terminal, ensure each print is visible
-                output.push_str(content.as_str());
             }
+            output.push_str(content.as_str());
         }
         if let Some(u) = data.usage {
-            usage.total_tokens += u.total_tokens;
-            usage.prompt_tokens += u.prompt_tokens;
-            usage.completion_tokens += u.completion_tokens;
+            usage += u;
         }
     }
-    println!("\n{}", end_separator);
+    if echo {
+        println!("\n{}", end_separator);
+    }
     Ok((output, usage))
 }
 
-fn git_commit_prompt(diff_content: &str, hint: Option<&String>, prompt: String) -> Vec<llm::Message> {
+fn git_commit_prompt(changes: &str, hint: Option<&String>, prompt: String) -> Vec<llm::Message> {
+    git_commit_conversation(
+        prompt,
+        format!(
+            "Generate commit message for these changes. \
+            If it's a new file, focus on its purpose rather than analyzing its content:\n\
+            {changes}\n\n\
+            Output should be only json format, and without comments and explanations.\n"
+        ),
+        hint,
+    )
+}
+
+fn git_split_prompt(changes: &str, filenames: &[String], hint: Option<&String>, prompt: String) -> Vec<llm::Message> {
+    let files = filenames
+        .iter()
+        .map(|f| format!("- {f}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    git_commit_conversation(
+        prompt,
+        format!(
+            "Split these staged changes into logical commits and generate a commit message for each of them.\n\
+            Staged files:\n{files}\n\n\
+            {changes}\n\n\
+            Output should be only json format, and without comments and explanations.\n"
+        ),
+        hint,
+    )
+}
+
+fn git_squash_prompt(changes: &str, commits: &[String], hint: Option<&String>, prompt: String) -> Vec<llm::Message> {
+    let commits = commits
+        .iter()
+        .map(|c| format!("```text\n{c}\n```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    git_commit_conversation(
+        prompt,
+        format!(
+            "Generate the commit message of a squash merge, replacing these commit messages, oldest first:\n\
+            {commits}\n\n\
+            Describe the result of all the commits, not their history, and put the important details in the body. \
+            Leave out trailers such as Co-authored-by, they are kept as they are.\n\n\
+            The combined changes:\n\
+            {changes}\n\n\
+            Output should be only json format, and without comments and explanations.\n"
+        ),
+        hint,
+    )
+}
+
+fn git_commit_conversation(prompt: String, changes: String, hint: Option<&String>) -> Vec<llm::Message> {
     let mut messages = Vec::new();
     messages.push(llm::Message::new_system(prompt));
-    messages.push(llm::Message::new_user(format!(
-        "Generate commit message for these changes. \
-        If it's a new file, focus on its purpose rather than analyzing its content:\n\
-        ```diff\n{diff_content}\n```\n\n\
-        Output should be only json format, and without comments and explanations.\n"
-    )));
+    messages.push(llm::Message::new_user(changes));
     if let Some(p) = hint {
         messages.push(llm::Message::new_user(format!("hint: {p}")));
     }
@@ -82,7 +309,28 @@ fn git_commit_prompt(diff_content: &str, hint: Option<&String>, prompt: String)
     messages
 }
 
-fn print_configuration(model: &String, diff_content: &str, option: &ModelParameters, url: &String, args: &CommandArgs) {
+fn git_commit_feedback(feedback: Option<&str>) -> llm::Message {
+    let request = match feedback {
+        Some(f) => format!("Regenerate the commit messages, taking this feedback into account: {f}"),
+        None => "Regenerate the commit messages with a different wording.".to_string(),
+    };
+    llm::Message::new_user(format!(
+        "{request}\n\n\
+        Output should be only json format, and without comments and explanations.\n"
+    ))
+}
+
+pub(super) fn print_configuration(
+    model_config: &ModelConfig,
+    diff_content: &str,
+    option: &ModelParameters,
+    url: &str,
+    strategy: &DiffStrategy,
+    args: &CommandArgs,
+) {
+    if args.output == Output::Json {
+        return;
+    }
     println!(
         "\n{} {} {}",
         "⚙️".bright_cyan(),
@@ -95,7 +343,11 @@ fn print_configuration(model: &String, diff_content: &str, option: &ModelParamet
         args.language.bright_green().bold()
     );
     // model
-    println!("  {} Model: {}", "🚀".bright_yellow(), model.bright_green().bold());
+    println!(
+        "  {} Model: {}",
+        "🚀".bright_yellow(),
+        model_config.model.bright_green().bold()
+    );
     println!(
         "  {} Max Tokens: {}",
         "⚡".bright_yellow(),
@@ -121,11 +373,27 @@ fn print_configuration(model: &String, diff_content: &str, option: &ModelParamet
         "📑".bright_yellow(),
         diff_content.lines().count().to_string().bright_green().bold()
     );
+    let strategy = match strategy {
+        DiffStrategy::Truncated => strategy.to_string().bright_red().bold(),
+        _ => strategy.to_string().bright_green().bold(),
+    };
+    println!("  {} Diff Strategy: {}", "🧩".bright_yellow(), strategy);
+    println!(
+        "  {} Output: {}",
+        "📐".bright_yellow(),
+        if model_config.structured_output {
+            "json_schema"
+        } else {
+            "free-form json"
+        }
+        .bright_green()
+        .bold()
+    );
     println!("  {} Endpoint: {}\n", "🌐".bright_yellow(), url.bright_green());
 }
 fn fix_json_response(text: &str) -> String {
     let mut buffer = String::new();
-    let mut is_closed = false;
+    let mut is_closed;
     let mut json_stack = Vec::new();
     let mut is_inside_string = false;
     let mut json_escaped = false;
@@ -140,34 +408,28 @@ fn fix_json_response(text: &str) -> String {
                 is_inside_string = false;
             } else if c == '\n' && !json_escaped {
                 s = "\\n".to_string();
-            } else if c == '\\' {
-                json_escaped = true;
             } else {
-                json_escaped = false;
+                json_escaped = c == '\\';
             }
-        } else {
-            if c == '"' && in_json {
-                is_inside_string = true;
-            } else if c == '{' {
-                if json_stack.len() == 0 {
-                    json_stack.push(']');
-                    buffer.push_str("[");
-                }
-
-                json_stack.push('}');
-            } else if c == '[' {
+        } else if c == '"' && in_json {
+            is_inside_string = true;
+        } else if c == '{' {
+            if json_stack.is_empty() {
                 json_stack.push(']');
-            } else if c == '}' || c == ']' {
-                if !json_stack.is_empty() && c.eq(json_stack.last().unwrap()) {
-                    json_stack.pop();
-                    if json_stack.len() == 0 {
-                        is_closed = true;
-                    }
-                }
+                buffer.push('[');
+            }
+
+            json_stack.push('}');
+        } else if c == '[' {
+            json_stack.push(']');
+        } else if (c == '}' || c == ']') && !json_stack.is_empty() && c.eq(json_stack.last().unwrap()) {
+            json_stack.pop();
+            if json_stack.is_empty() {
+                is_closed = true;
             }
         }
 
-        if !in_json && json_stack.len() > 0 {
+        if !in_json && !json_stack.is_empty() {
             in_json = true;
         }
         if in_json {
@@ -179,7 +441,7 @@ fn fix_json_response(text: &str) -> String {
         }
     }
     for i in 0..json_stack.len() {
-        buffer.push_str(&json_stack[json_stack.len() - i - 1].to_string());
+        buffer.push(json_stack[json_stack.len() - i - 1]);
     }
     buffer
 
@@ -230,61 +492,105 @@ fn extract_json_content(text: &str) -> String {
     text.to_string()
 }
 
-fn process_llm_response(response: String, reference: Option<&String>) -> Result<Vec<String>> {
+/// The array of commit messages of the `{"commits": [...]}` object the prompt asks for, which the repair wraps into
+/// an array. Custom prompts may still ask for the array alone.
+fn unwrap_commits(value: Value) -> Value {
+    match value {
+        Value::Array(mut items) if items.len() == 1 && items[0].get("commits").is_some() => items[0]["commits"].take(),
+        Value::Object(mut object) if object.contains_key("commits") => object.remove("commits").unwrap_or_default(),
+        value => value,
+    }
+}
+
+/// Parses the commit messages of the response, which must propose at least one of them.
+fn parse_commits(message: &str, structured: bool) -> Result<Vec<CommitMessage>> {
+    // structured outputs are parsed strictly, the repair path is only the fallback
+    let commits = match structured.then(|| serde_json::from_str::<CommitMessages>(message)) {
+        Some(Ok(structured)) => structured.commits,
+        _ => process_llm_response(message)?,
+    };
+    if commits.is_empty() {
+        return Err(anyhow::anyhow!("the model returned no commit message"));
+    }
+    Ok(commits)
+}
+
+fn process_llm_response(response: &str) -> Result<Vec<CommitMessage>> {
     // 首先尝试提取代码块内容
-    let content = extract_json_content(&response);
+    let content = extract_json_content(response);
 
     // 尝试修复和解析 JSON
     let fixed_json = fix_json_response(&content);
 
-    match serde_json::from_str::<Vec<CommitMessage>>(&fixed_json) {
-        Ok(messages) => Ok(messages
-            .into_iter()
-            .map(|msg| {
-                let mut commit = String::new();
-
-                // 构建提交消息头
-                if let Some(scope) = msg.scope.filter(|s| !s.trim().is_empty()) {
-                    commit.push_str(&format!("{}({}): {}", msg.r#type, scope.trim(), msg.subject.trim()));
-                } else {
-                    commit.push_str(&format!("{}: {}", msg.r#type, msg.subject.trim()));
-                }
-
-                // 添加 issue 引用
-                if let Some(r) = reference {
-                    commit.push_str(" ");
-                    commit.push_str(r.as_str());
-                }
-
-                // 添加可选的消息体
-                if let Some(body) = msg.body.filter(|s| !s.trim().is_empty()) {
-                    commit.push_str("\n\n");
-                    commit.push_str(&theme::wrap_text(body.trim(), 100));
-                }
-
-                // 添加可选的页脚
-                if let Some(footer) = msg.footer.filter(|s| !s.trim().is_empty()) {
-                    commit.push_str("\n\n");
-                    commit.push_str(&theme::wrap_text(footer.trim(), 100));
-                }
-
-                commit
-            })
-            .collect()),
-        Err(e) => {
-            println!("Parse JSON failed: {}", e);
-            Err(anyhow::anyhow!("Parse JSON failed: {}", e))
-        }
-    }
+    serde_json::from_str::<Value>(&fixed_json)
+        .and_then(|v| serde_json::from_value(unwrap_commits(v)))
+        .map_err(|e| anyhow::anyhow!("Parse JSON failed: {}", e))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct CommitMessage {
-    r#type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    scope: Option<String>,
-    subject: String,
-    body: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    footer: Option<String>,
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_plan_diff() {
+        let file = |name: &str, lines: usize| {
+            let header = format!("diff --git a/{name} b/{name}\n--- a/{name}\n+++ b/{name}\n@@ -0,0 +1,{lines} @@\n");
+            header + &"+line\n".repeat(lines)
+        };
+        let diff = DiffConfig {
+            max_tokens: 100,
+            max_chunks: 2,
+        };
+        assert_eq!(plan_diff(&file("a", 10), &diff), (DiffStrategy::Single, vec![]));
+
+        let (strategy, chunks) = plan_diff(&format!("{}{}", file("a", 40), file("b", 40)), &diff);
+        assert_eq!((strategy, chunks.len()), (DiffStrategy::MapReduce { chunks: 2 }, 2));
+
+        // a single hunk over the budget can only be cut
+        let (strategy, chunks) = plan_diff(&file("a", 400), &diff);
+        assert_eq!((strategy, chunks.len()), (DiffStrategy::Truncated, 1));
+
+        // more chunks than allowed are not summarised one request each
+        let (strategy, chunks) = plan_diff(&format!("{}{}{}", file("a", 40), file("b", 40), file("c", 40)), &diff);
+        assert_eq!((strategy, chunks.len()), (DiffStrategy::Truncated, 1));
+    }
+
+    #[test]
+    fn test_parse_commits_empty() {
+        let response = r#"{"commits": []}"#;
+        assert!(parse_commits(response, true).is_err());
+        assert!(parse_commits(response, false).is_err());
+
+        let response = r#"{"commits": [{"type": "fix", "subject": "handle empty diff"}]}"#;
+        assert_eq!(parse_commits(response, true).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_process_llm_response_with_files() {
+        let response = r#"```json
+[
+  {"type": "feat", "scope": "parser", "subject": "add json parser", "files": ["src/parser.rs"]},
+  {"type": "docs", "subject": "document parser", "body": "", "files": ["README.md"]}
+]
+```"#;
+        let commits = process_llm_response(response).unwrap();
+
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].format(None), "feat(parser): add json parser");
+        assert_eq!(commits[1].format(Some(&"#12".to_string())), "docs: document parser #12");
+        assert_eq!(commits[1].files.as_deref(), Some(&["README.md".to_string()][..]));
+    }
+
+    #[test]
+    fn test_process_llm_response_object() {
+        let response = r#"{"commits": [{"type": "fix", "subject": "handle empty diff"}, {"type": "refactor", "subject": "split parser", "body": "Moves the lexer out.
+Keeps the API."}]}"#;
+        let commits = process_llm_response(response).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].format(None), "fix: handle empty diff");
+        assert_eq!(
+            commits[1].format(None),
+            "refactor: split parser\n\nMoves the lexer out.\nKeeps the API."
+        );
+    }
 }
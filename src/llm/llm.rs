@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 //     async fn chat_stream(&self, messages: Vec<Message>) -> Result<Iterator<Item=String>>
 // }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
     pub content: String,
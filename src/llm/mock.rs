@@ -0,0 +1,119 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::JoinHandle;
+
+/// A request received by the mock server
+pub struct MockRequest {
+    pub request_line: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+/// A response of the mock server
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+    interrupted: bool,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, content_type: &str, body: &str) -> MockResponse {
+        MockResponse {
+            status,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.to_string(),
+            interrupted: false,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> MockResponse {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Closes the connection before the end of the announced body, as a reset connection does
+    pub fn interrupted(mut self) -> MockResponse {
+        self.interrupted = true;
+        self
+    }
+}
+
+/// Starts a local HTTP server answering a single request with the given response.
+///
+/// Returns the server base URL and a handle resolving to the received request.
+pub fn serve_once(status: u16, content_type: &str, body: &str) -> (String, JoinHandle<MockRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let response = MockResponse::new(status, content_type, body);
+    (url, std::thread::spawn(move || answer(&listener, &response)))
+}
+
+/// Starts a local HTTP server answering one request with each of the responses, in order.
+///
+/// Returns the server base URL and a handle resolving to the received requests.
+pub fn serve(responses: Vec<MockResponse>) -> (String, JoinHandle<Vec<MockRequest>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || responses.iter().map(|r| answer(&listener, r)).collect());
+    (url, handle)
+}
+
+fn answer(listener: &TcpListener, response: &MockResponse) -> MockRequest {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+    let length = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .map(|(_, v)| v.parse::<usize>().unwrap())
+        .unwrap_or(0);
+    let mut request_body = vec![0; length];
+    reader.read_exact(&mut request_body).unwrap();
+
+    write!(stream, "HTTP/1.1 {} MOCK\r\n", response.status).unwrap();
+    for (name, value) in &response.headers {
+        write!(stream, "{name}: {value}\r\n").unwrap();
+    }
+    write!(
+        stream,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.body.len() + if response.interrupted { 1024 } else { 0 },
+        response.body
+    )
+    .unwrap();
+    stream.flush().unwrap();
+
+    MockRequest {
+        request_line: request_line.trim_end().to_string(),
+        headers,
+        body: String::from_utf8(request_body).unwrap(),
+    }
+}
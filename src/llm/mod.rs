@@ -1,14 +1,39 @@
+mod anthropic;
+mod changelog;
+mod commit;
 mod git_commit;
 mod interaction;
+#[allow(clippy::module_inception)]
 mod llm;
+#[cfg(test)]
+mod mock;
+mod ollama;
 mod openai;
+mod provider;
+mod pull_request;
+mod retry;
+mod style;
+mod summarize;
 mod theme;
 
 use crate::args::CommandArgs;
 use crate::config;
-use crate::config::{ModelConfig, ModelParameters};
-use crate::llm::git_commit::generate_git_commit_messages;
-use crate::prompt::Prompt;
+use crate::conventional::lint::{self, LintRules};
+use crate::conventional::COMMIT_TYPES;
+use crate::config::{DiffConfig, ModelConfig, ModelParameters};
+use crate::llm::changelog::polish_changelog;
+use crate::llm::git_commit::{
+    generate_git_commit_groups, generate_git_commit_messages, generate_squash_commit_messages,
+    regenerate_git_commit_messages, rewrite_git_commit_message,
+};
+use crate::llm::ollama::OllamaClient;
+use crate::llm::pull_request::generate_pull_request;
+use crate::prompt::{Prompt, CHANGELOG_PROMPT, PR_PROMPT, SPLIT_PROMPT};
+use crate::redact;
+pub use crate::llm::commit::CommitMessage;
+pub use crate::llm::provider::{ProviderKind, TokenUsage};
+pub use crate::llm::pull_request::PullRequest;
+pub use crate::llm::style::CommitStyle;
 use anyhow::{anyhow, Error, Result};
 use clap::ValueEnum;
 use colored::Colorize;
@@ -28,6 +53,9 @@ pub enum PromptModelVendor {
     #[clap(name = "ollama")]
     #[serde(rename = "ollama")]
     Ollama,
+    #[clap(name = "anthropic")]
+    #[serde(rename = "anthropic")]
+    Anthropic,
 }
 
 impl PromptModelVendor {
@@ -35,7 +63,41 @@ impl PromptModelVendor {
         match self {
             PromptModelVendor::OpenAI => "gpt-3.5-turbo".to_string(),
             PromptModelVendor::DeepSeek => "deepseek-chat".to_string(),
-            PromptModelVendor::Ollama => "ollama".to_string(),
+            PromptModelVendor::Ollama => "llama3.2".to_string(),
+            PromptModelVendor::Anthropic => "claude-3-5-haiku-latest".to_string(),
+        }
+    }
+
+    /// Returns the API protocol the vendor speaks
+    pub fn kind(&self) -> ProviderKind {
+        match self {
+            PromptModelVendor::OpenAI | PromptModelVendor::DeepSeek => ProviderKind::OpenAI,
+            PromptModelVendor::Anthropic => ProviderKind::Anthropic,
+            PromptModelVendor::Ollama => ProviderKind::Ollama,
+        }
+    }
+}
+
+/// Returns the model to use for a vendor when none is given.
+///
+/// For Ollama this is the first model installed on the server, falling back to the vendor default.
+pub fn default_model(vendor: &PromptModelVendor, base_url: &str) -> String {
+    if *vendor != PromptModelVendor::Ollama {
+        return vendor.default_model();
+    }
+
+    match OllamaClient::new(base_url.to_string(), String::new()).list_models() {
+        Ok(models) if !models.is_empty() => {
+            eprintln!("Installed models: {}", models.join(", ").bright_green());
+            models[0].clone()
+        }
+        Ok(_) => {
+            eprintln!("No models installed on the Ollama server, using {}", vendor.default_model());
+            vendor.default_model()
+        }
+        Err(e) => {
+            eprintln!("List Ollama models failed: {}", e);
+            vendor.default_model()
         }
     }
 }
@@ -44,12 +106,164 @@ impl PromptModelVendor {
 pub struct LLMResult {
     pub commit_message: String,
     pub commit_messages: Vec<String>,
+    /// Commit messages as parsed from the response, `commit_messages` holds them rendered
+    pub candidates: Vec<CommitMessage>,
     pub completion_tokens: i64,
     pub prompt_tokens: i64,
     pub total_tokens: i64,
+    /// Requests sent again after a transient failure
+    pub retries: u32,
+    /// Files of each commit message, only filled when splitting a change-set
+    pub commit_files: Vec<Vec<String>>,
+    /// Conversation sent to the model, used as the context when regenerating
+    pub messages: Vec<llm::Message>,
+    /// JSON schema of the structured output, when the vendor supports it
+    pub response_schema: Option<serde_json::Value>,
 }
 
-pub fn llm_request(diff_content: &str, _prompt: Prompt, args: &CommandArgs) -> Result<LLMResult> {
+impl LLMResult {
+    fn add_usage(&mut self, usage: TokenUsage) {
+        self.completion_tokens += usage.completion_tokens;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.total_tokens += usage.total_tokens;
+        self.retries += usage.retries;
+    }
+
+    /// Tokens used to generate the result
+    pub fn usage(&self) -> TokenUsage {
+        TokenUsage {
+            completion_tokens: self.completion_tokens,
+            prompt_tokens: self.prompt_tokens,
+            total_tokens: self.total_tokens,
+            retries: self.retries,
+        }
+    }
+}
+
+pub fn llm_request(diff_content: &str, _prompt: Prompt, style: &CommitStyle, args: &CommandArgs) -> Result<LLMResult> {
+    let (model_config, model_params) = load_model_config(args)?;
+    let config = config::get_config()?;
+    let diff_content = redact::redact_diff(diff_content, &config.redact)?;
+    get_commit_message(&diff_content, &model_config, model_params, &config.diff, style, args)
+}
+
+/// Ask the model for the message of a squash commit
+///
+/// # Arguments
+/// * `diff_content` - The combined diff of the squashed commits
+/// * `commits` - The messages of the squashed commits, oldest first
+pub fn llm_squash_request(
+    diff_content: &str,
+    commits: &[String],
+    style: &CommitStyle,
+    args: &CommandArgs,
+) -> Result<LLMResult> {
+    let (model_config, model_params) = load_model_config(args)?;
+    let config = config::get_config()?;
+    let diff_content = redact::redact_diff(diff_content, &config.redact)?;
+    let template = args.prompt_template.as_deref().unwrap_or(args.prompt.value());
+    let rendered_prompt = render_prompt(template, args.number_of_commit_options, &args.language, style)?;
+    generate_squash_commit_messages(
+        &diff_content,
+        commits,
+        &model_config,
+        model_params,
+        &config.diff,
+        args,
+        rendered_prompt,
+    )
+    .map_err(|e| anyhow!("request failed: {:?}", e))
+}
+
+/// Ask the model to split the staged changes into several commits, see [`LLMResult::commit_files`]
+pub fn llm_split_request(
+    diff_content: &str,
+    filenames: &[String],
+    style: &CommitStyle,
+    args: &CommandArgs,
+) -> Result<LLMResult> {
+    let (model_config, model_params) = load_model_config(args)?;
+    let config = config::get_config()?;
+    let diff_content = redact::redact_diff(diff_content, &config.redact)?;
+    let rendered_prompt = render_split_prompt(&args.language, style)?;
+    generate_git_commit_groups(
+        &diff_content,
+        filenames,
+        &model_config,
+        model_params,
+        &config.diff,
+        args,
+        rendered_prompt,
+    )
+    .map_err(|e| anyhow!("request failed: {:?}", e))
+}
+
+/// Ask the model for the title and description of a pull request
+///
+/// # Arguments
+/// * `diff_content` - The diff of the branch against its base
+/// * `commits` - The commit messages of the branch, oldest first
+pub fn llm_pull_request(
+    diff_content: &str,
+    commits: &[String],
+    style: &CommitStyle,
+    args: &CommandArgs,
+) -> Result<PullRequest> {
+    let (model_config, model_params) = load_model_config(args)?;
+    let config = config::get_config()?;
+    let diff_content = redact::redact_diff(diff_content, &config.redact)?;
+    let rendered_prompt = render_prompt(PR_PROMPT, 1, &args.language, style)?;
+    generate_pull_request(
+        &diff_content,
+        commits,
+        &model_config,
+        model_params,
+        &config.diff,
+        args,
+        rendered_prompt,
+    )
+    .map_err(|e| anyhow!("request failed: {:?}", e))
+}
+
+/// Ask the model to rewrite a commit message breaking the conventions, see [`rewrite_git_commit_message`]
+pub fn llm_rewrite_message(
+    message: &str,
+    problems: &[String],
+    style: &CommitStyle,
+    args: &CommandArgs,
+) -> Result<LLMResult> {
+    let (model_config, model_params) = load_model_config(args)?;
+    let template = args.prompt_template.as_deref().unwrap_or(args.prompt.value());
+    let rendered_prompt = render_prompt(template, 1, &args.language, style)?;
+    rewrite_git_commit_message(message, problems, &model_config, model_params, args, rendered_prompt)
+        .map_err(|e| anyhow!("request failed: {:?}", e))
+}
+
+/// Ask the model to rewrite the entries of a changelog into user-facing prose, keeping its Markdown structure
+pub fn llm_polish_changelog(changelog: &str, args: &CommandArgs) -> Result<(String, TokenUsage)> {
+    let (model_config, model_params) = load_model_config(args)?;
+    let rendered_prompt = render_prompt(CHANGELOG_PROMPT, 1, &args.language, &CommitStyle::default())?;
+    polish_changelog(changelog, &model_config, model_params, rendered_prompt)
+        .map_err(|e| anyhow!("request failed: {:?}", e))
+}
+
+/// Regenerate commit messages by continuing the conversation of a previous result,
+/// optionally with the user's feedback as a follow-up turn.
+pub fn llm_retry(previous: &LLMResult, feedback: Option<&str>, args: &CommandArgs) -> Result<LLMResult> {
+    let (model_config, model_params) = load_model_config(args)?;
+    let mut messages = previous.messages.clone();
+    messages.push(llm::Message::new_assistant(previous.commit_message.clone()));
+    let schema = previous.response_schema.clone();
+    regenerate_git_commit_messages(messages, feedback, schema, &model_config, model_params, args)
+        .map_err(|e| anyhow!("request failed: {:?}", e))
+}
+
+/// Model the requests are sent to
+pub fn model_name(args: &CommandArgs) -> Result<String> {
+    Ok(load_model_config(args)?.0.model)
+}
+
+fn load_model_config(args: &CommandArgs) -> Result<(ModelConfig, ModelParameters)> {
     let config = config::get_config()?;
     let model_config = config
         .load_model(args.vendor.clone())
@@ -59,36 +273,61 @@ pub fn llm_request(diff_content: &str, _prompt: Prompt, args: &CommandArgs) -> R
     if let Some(m) = args.model.as_ref() {
         mc.model = m.clone()
     }
-    get_commit_message(diff_content, &mc, config.model_params(), args)
+    mc.http = config.default.http();
+    Ok((mc, config.model_params()))
 }
 
 fn get_commit_message(
     diff_content: &str,
     model_config: &ModelConfig,
     model_option: ModelParameters,
+    diff: &DiffConfig,
+    style: &CommitStyle,
     args: &CommandArgs,
 ) -> Result<LLMResult> {
-    let rendered_prompt = render_prompt(args.prompt, args.number_of_commit_options, &args.language)?;
-    let result = generate_git_commit_messages(diff_content, model_config, model_option, args, rendered_prompt)
-        .map_err(|e| anyhow!("request failed: {:?}", e))?;
+    let template = args.prompt_template.as_deref().unwrap_or(args.prompt.value());
+    let rendered_prompt = render_prompt(template, args.number_of_commit_options, &args.language, style)?;
+    let result =
+        generate_git_commit_messages(diff_content, model_config, model_option, diff, args, rendered_prompt)
+            .map_err(|e| anyhow!("request failed: {:?}", e))?;
     Ok(result)
 }
 
-fn render_prompt(prompt: Prompt, number: u8, language: &String) -> Result<String, Error> {
-    let p = prompt.value();
-
+fn render_prompt(template: &str, number: u8, language: &str, style: &CommitStyle) -> Result<String, Error> {
     let mut env = Environment::new();
-    env.add_template("prompt", p)?;
+    env.add_template("prompt", template)?;
     let tmpl = env.get_template("prompt")?;
     let rendered = tmpl.render(context! {
         number => number,
         language => map_language(language),
+        examples => style.examples,
+        scopes => style.scopes,
+        types => COMMIT_TYPES,
+        schema => schema_json(CommitMessage::prompt_schema(Some(number), false))?,
     })?;
     Ok(rendered)
 }
 
-fn map_language(lang: &String) -> &str {
-    match lang.as_str().to_lowercase().as_str() {
+fn render_split_prompt(language: &str, style: &CommitStyle) -> Result<String, Error> {
+    let mut env = Environment::new();
+    env.add_template("split", SPLIT_PROMPT)?;
+    let tmpl = env.get_template("split")?;
+    let rendered = tmpl.render(context! {
+        language => map_language(language),
+        examples => style.examples,
+        scopes => style.scopes,
+        types => COMMIT_TYPES,
+        schema => schema_json(CommitMessage::prompt_schema(None, true))?,
+    })?;
+    Ok(rendered)
+}
+
+fn schema_json(schema: serde_json::Value) -> Result<String, Error> {
+    Ok(serde_json::to_string_pretty(&schema)?)
+}
+
+fn map_language(lang: &str) -> &str {
+    match lang.to_lowercase().as_str() {
         "en" => "English",
         "zh" => "Chinese",
         "ja" => "Japanese",
@@ -105,43 +344,126 @@ fn map_language(lang: &String) -> &str {
         "sv" => "Swedish",
         "fi" => "Finnish",
         "hu" => "Hungarian",
-        _ => lang.as_str(),
+        _ => lang,
     }
 }
 
 pub enum Confirm<'a> {
     Ok(&'a String),
-    Retry,
+    /// Open the message in the editor before committing it
+    Edit(&'a String),
+    /// Regenerate the commit messages, with optional free-text feedback for the model
+    Retry(Option<String>),
     Exit,
 }
 
-pub fn confirm_commit<'a>(result: &'a LLMResult) -> Result<Confirm<'a>, &'static str> {
-    theme::print_commit_options(result, theme::DEFAULT_COMMIT_OPTION_STYLE);
-    let input = user_choice(result);
-    match input.as_str() {
-        "" => Ok(Confirm::Ok(&result.commit_messages[0])),
+/// Shows the generated commit messages with their lint violations, and asks which one to commit until the answer
+/// is valid
+pub fn confirm_commit<'a>(result: &'a LLMResult, rules: &LintRules) -> Confirm<'a> {
+    let violations: Vec<_> = result.commit_messages.iter().map(|m| lint::lint(m, rules)).collect();
+    theme::print_commit_options(result, &violations, theme::DEFAULT_COMMIT_OPTION_STYLE);
+    loop {
+        match parse_choice(&user_choice(result), result) {
+            Ok(confirm) => return confirm,
+            Err(e) => eprintln!("{}", e.red()),
+        }
+    }
+}
+
+fn parse_choice<'a>(input: &str, result: &'a LLMResult) -> Result<Confirm<'a>, &'static str> {
+    if let Some(feedback) = parse_retry(input) {
+        return Ok(Confirm::Retry(feedback));
+    }
+    match input.to_lowercase().as_str() {
+        "" => pick(1, result).map(Confirm::Ok),
         "n" => Ok(Confirm::Exit),
-        num => {
-            if let Ok(choice) = num.parse::<usize>() {
-                if choice > 0 && choice <= result.commit_messages.len() {
-                    Ok(Confirm::Ok(&result.commit_messages[choice - 1]))
-                } else {
-                    Err("Invalid input choice")
-                }
+        "e" => pick(1, result).map(Confirm::Edit),
+        choice => {
+            if let Ok(number) = choice.parse::<usize>() {
+                pick(number, result).map(Confirm::Ok)
+            } else if let Some(Ok(number)) = choice.strip_prefix('e').map(str::parse::<usize>) {
+                pick(number, result).map(Confirm::Edit)
             } else {
-                Err("Input must be a number")
+                Err("Invalid input choice")
             }
         }
     }
 }
 
+/// `r` regenerates, `r <feedback>` regenerates with free-text feedback for the model.
+///
+/// Only the explicit prefix regenerates, a typo must not send a paid request.
+fn parse_retry(input: &str) -> Option<Option<String>> {
+    let rest = input.strip_prefix(['r', 'R'])?;
+    if rest.is_empty() {
+        return Some(None);
+    }
+    rest.starts_with(char::is_whitespace).then(|| Some(rest.trim().to_string()))
+}
+
+fn pick(number: usize, result: &LLMResult) -> Result<&String, &'static str> {
+    if number > 0 && number <= result.commit_messages.len() {
+        Ok(&result.commit_messages[number - 1])
+    } else {
+        Err("Invalid input choice")
+    }
+}
+
+/// Answer to a proposed split of the staged changes
+pub enum SplitConfirm {
+    /// Commit every group in order
+    Commit,
+    /// Regenerate the split, with optional free-text feedback for the model
+    Retry(Option<String>),
+    Exit,
+}
+
+pub fn confirm_split(result: &LLMResult) -> SplitConfirm {
+    theme::print_commit_groups(result, theme::DEFAULT_COMMIT_OPTION_STYLE);
+    loop {
+        print!(
+            "\n{} {} {} {} {}\n{} ",
+            "🎯".bright_yellow(),
+            format!("Commit all {} groups?", result.commit_messages.len()).bright_cyan().bold(),
+            "(r [feedback]: regenerate)".bright_magenta(),
+            "•".bright_yellow(),
+            "(n: cancel)".bright_red(),
+            "⌲ Enter your choice (default: y): ".bright_yellow()
+        );
+        let mut input = String::new();
+
+        std::io::stdout().flush().expect("Failed to flush stdout");
+        std::io::stdin().read_line(&mut input).expect("Failed to read line");
+
+        match parse_split_choice(input.trim()) {
+            Some(confirm) => return confirm,
+            None => eprintln!("{}", "Invalid input choice".red()),
+        }
+    }
+}
+
+fn parse_split_choice(input: &str) -> Option<SplitConfirm> {
+    if let Some(feedback) = parse_retry(input) {
+        return Some(SplitConfirm::Retry(feedback));
+    }
+    match input.to_lowercase().as_str() {
+        "" | "y" => Some(SplitConfirm::Commit),
+        "n" => Some(SplitConfirm::Exit),
+        _ => None,
+    }
+}
+
 fn user_choice(result: &LLMResult) -> String {
     print!(
-        "\n{} {} {} {} {}\n{} ",
+        "\n{} {} {} {} {} {} {} {} {}\n{} ",
         "🎯".bright_yellow(),
         "Select Your Commit".bright_cyan().bold(),
         format!("[1-{}]", result.commit_messages.len()).bright_green(),
         "•".bright_yellow(),
+        "(e<N>: edit)".bright_blue(),
+        "•".bright_yellow(),
+        "(r [feedback]: regenerate)".bright_magenta(),
+        "•".bright_yellow(),
         "(n: cancel)".bright_red(),
         "⌲ Enter your choice (default: 1): ".bright_yellow()
     );
@@ -151,5 +473,78 @@ fn user_choice(result: &LLMResult) -> String {
     std::io::stdout().flush().expect("Failed to flush stdout");
     std::io::stdin().read_line(&mut input).expect("Failed to read line");
 
-    input.trim().to_lowercase()
+    input.trim().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn result_with(messages: &[&str]) -> LLMResult {
+        LLMResult {
+            commit_message: String::new(),
+            commit_messages: messages.iter().map(|s| s.to_string()).collect(),
+            candidates: vec![],
+            completion_tokens: 0,
+            prompt_tokens: 0,
+            total_tokens: 0,
+            retries: 0,
+            commit_files: vec![],
+            messages: vec![],
+            response_schema: None,
+        }
+    }
+
+    #[test]
+    fn test_render_prompt_with_style() {
+        let style = CommitStyle {
+            examples: vec!["feat(parser): support arrays".to_string()],
+            scopes: vec!["parser".to_string(), "cli".to_string()],
+        };
+
+        let rendered = render_prompt(Prompt::P1.value(), 2, "zh", &style).unwrap();
+        assert!(rendered.contains("language of commit subject and body: Chinese"));
+        assert!(rendered.contains("scope must be one of: parser, cli"));
+        assert!(rendered.contains("type must be one of: feat, fix, docs,"));
+        assert!(rendered.contains("feat(parser): support arrays"));
+
+        let rendered = render_prompt(Prompt::P1.value(), 2, "en", &CommitStyle::default()).unwrap();
+        assert!(!rendered.contains("scope must be one of"));
+        assert!(!rendered.contains("Repository Style"));
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        let result = result_with(&["feat: a", "fix: b"]);
+
+        assert!(matches!(parse_choice("", &result), Ok(Confirm::Ok(m)) if m == "feat: a"));
+        assert!(matches!(parse_choice("2", &result), Ok(Confirm::Ok(m)) if m == "fix: b"));
+        assert!(matches!(parse_choice("N", &result), Ok(Confirm::Exit)));
+        assert!(matches!(parse_choice("r", &result), Ok(Confirm::Retry(None))));
+        assert!(matches!(parse_choice("e", &result), Ok(Confirm::Edit(m)) if m == "feat: a"));
+        assert!(matches!(parse_choice("E2", &result), Ok(Confirm::Edit(m)) if m == "fix: b"));
+        assert!(parse_choice("e3", &result).is_err());
+        assert!(parse_choice("3", &result).is_err());
+        assert!(matches!(
+            parse_choice("r Shorter, scope should be parser", &result),
+            Ok(Confirm::Retry(Some(f))) if f == "Shorter, scope should be parser"
+        ));
+        // typos and other answers never regenerate
+        assert!(parse_choice("y", &result).is_err());
+        assert!(parse_choice("explain the parser", &result).is_err());
+        assert!(parse_choice("regenerate", &result).is_err());
+    }
+
+    #[test]
+    fn test_parse_split_choice() {
+        assert!(matches!(parse_split_choice(""), Some(SplitConfirm::Commit)));
+        assert!(matches!(parse_split_choice("Y"), Some(SplitConfirm::Commit)));
+        assert!(matches!(parse_split_choice("n"), Some(SplitConfirm::Exit)));
+        assert!(matches!(parse_split_choice("R"), Some(SplitConfirm::Retry(None))));
+        assert!(matches!(
+            parse_split_choice("r keep docs apart"),
+            Some(SplitConfirm::Retry(Some(f))) if f == "keep docs apart"
+        ));
+        assert!(parse_split_choice("yes please").is_none());
+    }
 }
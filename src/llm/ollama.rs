@@ -0,0 +1,232 @@
+use crate::config::{HttpConfig, ModelConfig, ModelParameters};
+use crate::llm::provider::{ChatChunk, ChatStream, Client, TokenUsage};
+use crate::llm::{llm, retry};
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+/// Client for the native Ollama API (`/api/chat`)
+pub struct OllamaClient {
+    base_url: String,
+    model: String,
+    client: reqwest::blocking::Client,
+    http: HttpConfig,
+}
+
+impl OllamaClient {
+    pub fn new_from_config(conf: &ModelConfig) -> OllamaClient {
+        OllamaClient {
+            base_url: conf.base_url.clone(),
+            model: conf.model.clone(),
+            client: retry::http_client(&conf.http),
+            http: conf.http,
+        }
+    }
+
+    pub fn new(base_url: String, model: String) -> OllamaClient {
+        let http = HttpConfig::default();
+        OllamaClient {
+            base_url,
+            model,
+            client: retry::http_client(&http),
+            http,
+        }
+    }
+
+    /// Lists the names of the models installed on the Ollama server
+    pub fn list_models(&self) -> anyhow::Result<Vec<String>> {
+        let response = self
+            .client
+            .get(format!("{}/api/tags", self.base_url.trim_end_matches('/')))
+            .timeout(Duration::from_secs(10))
+            .send()?;
+
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "HTTP request failed with status code {}, reason: {}",
+                response.status(),
+                response.text().unwrap_or("empty".to_string())
+            ));
+        }
+
+        let tags: OllamaTags = response.json()?;
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+}
+
+impl Client for OllamaClient {
+    fn endpoint(&self) -> &str {
+        &self.base_url
+    }
+
+    fn stream_chat(
+        &self,
+        messages: Vec<llm::Message>,
+        option: ModelParameters,
+        schema: Option<&Value>,
+    ) -> anyhow::Result<ChatStream> {
+        let mut payload = json!({
+            "model": self.model,
+            "messages": messages,
+            "options": {
+                "temperature": option.temperature,
+                "top_p": option.top_p,
+                "top_k": option.top_k,
+                "num_predict": option.max_tokens,
+            },
+            "keep_alive": "120m",
+            "stream": true,
+        });
+        if let Some(schema) = schema {
+            payload["format"] = schema.clone();
+        }
+
+        let (response, retries) = retry::send(&self.http, || {
+            self.client
+                .post(format!("{}/api/chat", self.base_url.trim_end_matches('/')))
+                .header("Content-Type", "application/json")
+                .json(&payload)
+        })?;
+
+        // the response is a stream of newline-delimited JSON objects
+        let reader = BufReader::new(response);
+        let stream: ChatStream = Box::new(
+            reader
+                .lines()
+                .map(|l| l.map_err(retry::stream_error))
+                .filter(|s| !matches!(s, Ok(s) if s.trim().is_empty()))
+                .map(|s| {
+                    let s = s?;
+                    let data: OllamaChatResponse =
+                        serde_json::from_str(&s).map_err(|e| anyhow!("invalid stream event: {e}, data: {s}"))?;
+                    data.into_chunk()
+                }),
+        );
+        Ok(retry::count_retries(retries, stream))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    prompt_eval_count: i64,
+    #[serde(default)]
+    eval_count: i64,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaMessage {
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+impl OllamaChatResponse {
+    fn into_chunk(self) -> anyhow::Result<ChatChunk> {
+        if let Some(error) = self.error {
+            return Err(anyhow!("ollama error: {error}"));
+        }
+
+        // token counts are only reported on the final object of the stream
+        let usage = self.done.then_some(TokenUsage {
+            completion_tokens: self.eval_count,
+            prompt_tokens: self.prompt_eval_count,
+            total_tokens: self.prompt_eval_count + self.eval_count,
+            retries: 0,
+        });
+        Ok(ChatChunk {
+            content: self.message.map(|m| m.content).filter(|c| !c.is_empty()),
+            usage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::llm::mock::{self, MockResponse};
+
+    const STREAM: &str = r#"{"model":"llama3.2","created_at":"2024-05-01T00:00:00Z","message":{"role":"assistant","content":"[{\"type\": \"fix\","},"done":false}
+{"model":"llama3.2","created_at":"2024-05-01T00:00:00Z","message":{"role":"assistant","content":" \"subject\": \"handle empty diff\"}]"},"done":false}
+{"model":"llama3.2","created_at":"2024-05-01T00:00:01Z","message":{"role":"assistant","content":""},"done_reason":"stop","done":true,"total_duration":4883583458,"prompt_eval_count":26,"eval_count":18}
+"#;
+
+    #[test]
+    fn test_stream_chat() {
+        let (url, server) = mock::serve_once(200, "application/x-ndjson", STREAM);
+        let client = OllamaClient::new(url, "llama3.2".to_string());
+
+        let option = ModelParameters {
+            temperature: 0.1,
+            top_p: 0.75,
+            top_k: 5,
+            max_tokens: 1024,
+        };
+        let chunks = client
+            .stream_chat(vec![llm::Message::new_user("diff".to_string())], option, None)
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+
+        let content: String = chunks.iter().filter_map(|c| c.content.clone()).collect();
+        assert_eq!(content, r#"[{"type": "fix", "subject": "handle empty diff"}]"#);
+        let usage = chunks.iter().filter_map(|c| c.usage).next_back().unwrap();
+        assert_eq!(
+            (usage.prompt_tokens, usage.completion_tokens, usage.total_tokens),
+            (26, 18, 44)
+        );
+
+        let request = server.join().unwrap();
+        assert_eq!(request.request_line, "POST /api/chat HTTP/1.1");
+        let body = request.json();
+        assert_eq!(body["model"], "llama3.2");
+        assert_eq!(body["options"]["top_k"], 5);
+        assert_eq!(body["options"]["num_predict"], 1024);
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn test_stream_chat_interrupted() {
+        let (url, server) = mock::serve(vec![MockResponse::new(200, "application/x-ndjson", STREAM).interrupted()]);
+        let client = OllamaClient::new(url, "llama3.2".to_string());
+
+        let option = ModelParameters {
+            temperature: 0.1,
+            top_p: 0.75,
+            top_k: 5,
+            max_tokens: 1024,
+        };
+        let error = client
+            .stream_chat(vec![llm::Message::new_user("diff".to_string())], option, None)
+            .unwrap()
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap_err();
+        assert!(error.to_string().contains("interrupted"), "{error}");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_list_models() {
+        let tags = r#"{"models":[{"name":"llama3.2:latest","model":"llama3.2:latest","size":2019393189},{"name":"qwen2.5-coder:7b","model":"qwen2.5-coder:7b","size":4683087332}]}"#;
+        let (url, server) = mock::serve_once(200, "application/json", tags);
+        let client = OllamaClient::new(url, String::new());
+
+        let models = client.list_models().unwrap();
+        assert_eq!(models, vec!["llama3.2:latest", "qwen2.5-coder:7b"]);
+        assert_eq!(server.join().unwrap().request_line, "GET /api/tags HTTP/1.1");
+    }
+}
@@ -1,24 +1,17 @@
-use crate::config::{ModelConfig, ModelParameters};
-use crate::llm::llm;
+use crate::config::{HttpConfig, ModelConfig, ModelParameters};
+use crate::llm::provider::{ChatChunk, ChatStream, Client, TokenUsage};
+use crate::llm::{llm, retry};
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Value};
 use std::io::{BufRead, BufReader};
-use std::time::Duration;
-
-pub trait Client {
-    fn stream_chat(
-        &self,
-        messages: Vec<llm::Message>,
-        option: ModelParameters,
-    ) -> Result<impl Iterator<Item = (OpenAIStreamResponse, String)>, anyhow::Error>;
-}
 
 pub struct OpenAIClient {
-    pub(crate) base_url: String,
+    base_url: String,
     model: String,
     api_key: Option<String>,
     client: reqwest::blocking::Client,
+    http: HttpConfig,
 }
 
 impl OpenAIClient {
@@ -28,90 +21,87 @@ impl OpenAIClient {
             base_url: conf.base_url.clone(),
             model,
             api_key: conf.api_key.clone(),
-            client: reqwest::blocking::Client::new(),
-        }
-    }
-
-    pub fn new(base_url: String, model: String, api_key: String) -> OpenAIClient {
-        OpenAIClient {
-            base_url,
-            model,
-            api_key: Some(api_key),
-            client: reqwest::blocking::Client::new(),
+            client: retry::http_client(&conf.http),
+            http: conf.http,
         }
     }
+}
 
-    pub fn new_with_client(
-        client: reqwest::blocking::Client,
-        base_url: String,
-        model: String,
-        api_key: String,
-    ) -> OpenAIClient {
-        OpenAIClient {
-            base_url,
-            model,
-            api_key: Some(api_key),
-            client,
-        }
+impl Client for OpenAIClient {
+    fn endpoint(&self) -> &str {
+        &self.base_url
     }
 
-    pub fn stream_chat(
+    fn stream_chat(
         &self,
         messages: Vec<llm::Message>,
         option: ModelParameters,
-    ) -> anyhow::Result<impl Iterator<Item = (OpenAIStreamResponse, String)>, anyhow::Error> {
-        let payload = &json!({
+        schema: Option<&Value>,
+    ) -> anyhow::Result<ChatStream> {
+        let mut payload = json!({
             "model": self.model,
             "messages": messages,
-            "options": {
-                "temperature": option.temperature,
-                "top_p": option.top_p,
-                "top_k": option.top_k,
-            },
-            "options": option,
-            "keep_alive": "120m",
+            "temperature": option.temperature,
+            "top_p": option.top_p,
             "max_tokens": option.max_tokens,
             "stream": true,
         });
-
-        let mut builder = self
-            .client
-            .post(format!("{}/chat/completions", self.base_url.trim_end_matches("/")))
-            .timeout(Duration::from_secs(120));
-        if let Some(key) = &self.api_key {
-            builder = builder.header("Authorization", format!("Bearer {}", key));
+        if let Some(schema) = schema {
+            payload["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": {"name": "conventional_commits", "strict": true, "schema": schema},
+            });
         }
-        let response = builder
-            .header("Accept", "text/event-stream")
-            .header("Content-Type", "application/json")
-            .json(payload)
-            .send()?;
 
-        if !response.status().is_success() {
-            return Err(anyhow!(
-                "HTTP request failed with status code {}, reason: {}",
-                response.status(),
-                response.text().unwrap_or("empty".to_string())
-            ));
-        }
+        let (response, retries) = retry::send(&self.http, || {
+            let mut builder = self
+                .client
+                .post(format!("{}/chat/completions", self.base_url.trim_end_matches("/")));
+            if let Some(key) = &self.api_key {
+                builder = builder.header("Authorization", format!("Bearer {}", key));
+            }
+            builder
+                .header("Accept", "text/event-stream")
+                .header("Content-Type", "application/json")
+                .json(&payload)
+        })?;
 
         let reader = BufReader::new(response);
-        Ok(reader
-            .lines()
-            .filter_map(|l| {
-                l.ok().and_then(|s| {
-                    if s.starts_with("data: ") {
-                        Some(s["data: ".len()..].to_string())
-                    } else {
-                        None
-                    }
+        let stream: ChatStream = Box::new(
+            reader
+                .lines()
+                .filter_map(|l| match l {
+                    Ok(s) => s.strip_prefix("data: ").map(|s| Ok(s.to_string())),
+                    Err(e) => Some(Err(retry::stream_error(e))),
                 })
-            })
-            .filter(|s| s != "[DONE]")
-            .map(|s| {
-                let json: OpenAIStreamResponse = serde_json::from_str(&s).unwrap();
-                (json, s)
-            }))
+                .filter(|s| !matches!(s, Ok(s) if s == "[DONE]"))
+                .map(|s| {
+                    let s = s?;
+                    let data: OpenAIStreamResponse =
+                        serde_json::from_str(&s).map_err(|e| anyhow!("invalid stream event: {e}, data: {s}"))?;
+                    Ok(data.into())
+                }),
+        );
+        Ok(retry::count_retries(retries, stream))
+    }
+}
+
+impl From<OpenAIStreamResponse> for ChatChunk {
+    fn from(data: OpenAIStreamResponse) -> Self {
+        let content: Option<String> = data
+            .choices
+            .into_iter()
+            .filter_map(|choice| choice.delta.content)
+            .reduce(|acc, c| acc + &c);
+        ChatChunk {
+            content,
+            usage: data.usage.map(|u| TokenUsage {
+                completion_tokens: u.completion_tokens,
+                prompt_tokens: u.prompt_tokens,
+                total_tokens: u.total_tokens,
+                retries: 0,
+            }),
+        }
     }
 }
 
@@ -139,30 +129,6 @@ pub struct OpenAIChoiceDelta {
     pub content: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-pub struct OpenAIResponse {
-    pub id: String,
-    pub model: String, // 生成该 completion 的模型名
-    pub object: String,
-    pub system_fingerprint: String, // This fingerprint represents the backend configuration that the model runs with.
-    pub choices: Vec<OpenAIResponseChoice>,
-    pub usage: OpenAIResponseUsage, // 该对话补全请求的用量信息
-    pub created: i64,               // 创建聊天完成时的 Unix 时间戳（以秒为单位）
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct OpenAIResponseChoice {
-    pub index: i64, // 该 completion 在模型生成的 completion 的选择列表中的索引。
-    pub message: OpenAIResponseChoiceMessage,
-    pub finish_reason: String, // 模型停止生成 token 的原因:stop/length/content_filter
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct OpenAIResponseChoiceMessage {
-    pub role: String, // 角色:assistant
-    pub content: String,
-}
-
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct OpenAIResponseUsage {
     pub completion_tokens: i64,
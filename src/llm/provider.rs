@@ -0,0 +1,77 @@
+use crate::config::{ModelConfig, ModelParameters};
+use crate::llm::anthropic::AnthropicClient;
+use crate::llm::llm;
+use crate::llm::ollama::OllamaClient;
+use crate::llm::openai::OpenAIClient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// API protocol spoken by a model vendor
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum ProviderKind {
+    /// OpenAI-compatible `/chat/completions` API
+    #[default]
+    #[serde(rename = "openai")]
+    OpenAI,
+    /// Anthropic Messages API
+    #[serde(rename = "anthropic")]
+    Anthropic,
+    /// Native Ollama `/api/chat` API
+    #[serde(rename = "ollama")]
+    Ollama,
+}
+
+/// Token usage reported by a provider
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct TokenUsage {
+    pub completion_tokens: i64,
+    pub prompt_tokens: i64,
+    pub total_tokens: i64,
+    /// Requests sent again after a transient failure, counted along to show in the stats
+    pub retries: u32,
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: TokenUsage) {
+        self.completion_tokens += other.completion_tokens;
+        self.prompt_tokens += other.prompt_tokens;
+        self.total_tokens += other.total_tokens;
+        self.retries += other.retries;
+    }
+}
+
+/// A single event of a streamed chat completion
+#[derive(Debug, Default)]
+pub struct ChatChunk {
+    /// Generated text of this event, if any
+    pub content: Option<String>,
+    /// Token usage, usually only present on the last event
+    pub usage: Option<TokenUsage>,
+}
+
+pub type ChatStream = Box<dyn Iterator<Item = anyhow::Result<ChatChunk>>>;
+
+/// A chat completion backend
+pub trait Client {
+    /// Endpoint the client sends requests to, for display purposes
+    fn endpoint(&self) -> &str;
+
+    /// Sends the conversation and returns the streamed completion.
+    ///
+    /// `schema` is the JSON schema the output must follow, for vendors supporting structured outputs.
+    fn stream_chat(
+        &self,
+        messages: Vec<llm::Message>,
+        option: ModelParameters,
+        schema: Option<&Value>,
+    ) -> anyhow::Result<ChatStream>;
+}
+
+/// Creates the client matching the `kind` of the model configuration
+pub fn new_client(conf: &ModelConfig) -> Box<dyn Client> {
+    match conf.kind {
+        ProviderKind::OpenAI => Box::new(OpenAIClient::new_from_config(conf, None)),
+        ProviderKind::Anthropic => Box::new(AnthropicClient::new_from_config(conf)),
+        ProviderKind::Ollama => Box::new(OllamaClient::new_from_config(conf)),
+    }
+}
@@ -0,0 +1,102 @@
+use crate::args::CommandArgs;
+use crate::config::{DiffConfig, ModelConfig, ModelParameters};
+use crate::llm::git_commit::{describe_changes, plan_diff, print_configuration, stream_chat_response};
+use crate::llm::llm;
+use crate::llm::provider::{new_client, TokenUsage};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+/// A pull request title and its Markdown description
+#[derive(Debug)]
+pub struct PullRequest {
+    pub title: String,
+    pub body: String,
+    pub usage: TokenUsage,
+}
+
+/// Ask the model for the pull request of a branch, from its commit messages and its diff against the base.
+pub(crate) fn generate_pull_request(
+    diff_content: &str,
+    commits: &[String],
+    model_config: &ModelConfig,
+    option: ModelParameters,
+    diff: &DiffConfig,
+    args: &CommandArgs,
+    prompt: String,
+) -> Result<PullRequest> {
+    let client = new_client(model_config);
+    let (strategy, chunks) = plan_diff(diff_content, diff);
+    print_configuration(model_config, diff_content, &option, client.endpoint(), &strategy, args);
+
+    let (changes, mut usage) = describe_changes(client.as_ref(), diff_content, &chunks, &strategy, &option)?;
+    let messages = pull_request_prompt(&changes, commits, args.hint.as_ref(), prompt);
+    let (output, completion_usage) = stream_chat_response(option, client.as_ref(), messages, None, true)?;
+    usage += completion_usage;
+
+    let (title, body) = parse_pull_request(&output)?;
+    Ok(PullRequest { title, body, usage })
+}
+
+fn pull_request_prompt(
+    changes: &str,
+    commits: &[String],
+    hint: Option<&String>,
+    prompt: String,
+) -> Vec<llm::Message> {
+    let commits = commits
+        .iter()
+        .map(|c| format!("```text\n{c}\n```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut messages = vec![
+        llm::Message::new_system(prompt),
+        llm::Message::new_user(format!(
+            "Write the pull request of a branch with these commits, oldest first:\n{commits}\n\n\
+            The changes of the branch:\n{changes}\n"
+        )),
+    ];
+    if let Some(p) = hint {
+        messages.push(llm::Message::new_user(format!("hint: {p}")));
+    }
+    messages
+}
+
+/// Splits the output into the title, its first line, and the description
+fn parse_pull_request(output: &str) -> Result<(String, String)> {
+    let re = Regex::new(r"(?s)<think>.*?</think>").unwrap();
+    let output = re.replace_all(output, "");
+    let mut output = output.trim();
+
+    // some models wrap the whole answer in a code block
+    if let Some(inner) = output.strip_prefix("```") {
+        let inner = inner.split_once('\n').map_or("", |(_, rest)| rest);
+        output = inner.trim_end().strip_suffix("```").unwrap_or(inner).trim();
+    }
+
+    let (title, body) = output.split_once('\n').unwrap_or((output, ""));
+    let title = title.trim().trim_start_matches('#').trim();
+    let title = title.strip_prefix("Title:").unwrap_or(title).trim();
+    if title.is_empty() {
+        return Err(anyhow!("no pull request title generated"));
+    }
+    Ok((title.to_string(), body.trim().to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_pull_request() {
+        let output = "<think>the branch adds a parser</think>\n```markdown\n# Title: Add TOML parser\n\n## Summary\nParses TOML.\n\n## Changes\n- add parser\n```";
+        let (title, body) = parse_pull_request(output).unwrap();
+        assert_eq!(title, "Add TOML parser");
+        assert_eq!(body, "## Summary\nParses TOML.\n\n## Changes\n- add parser");
+
+        assert_eq!(
+            parse_pull_request("Fix crash").unwrap(),
+            ("Fix crash".to_string(), String::new())
+        );
+        assert!(parse_pull_request("  \n").is_err());
+    }
+}
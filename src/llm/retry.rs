@@ -0,0 +1,212 @@
+use crate::config::HttpConfig;
+use crate::llm::provider::{ChatChunk, ChatStream, TokenUsage};
+use anyhow::anyhow;
+use colored::Colorize;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::time::{Duration, SystemTime};
+
+/// Delay before the first retry, doubled on each attempt
+const BASE_DELAY: Duration = Duration::from_secs(1);
+/// Longest wait between two attempts, `Retry-After` included
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Creates the HTTP client of a vendor with the configured timeouts.
+///
+/// The read timeout applies to every read of a streamed response, not to the whole completion.
+pub fn http_client(http: &HttpConfig) -> Client {
+    Client::builder()
+        .connect_timeout(http.connect_timeout)
+        .timeout(http.read_timeout)
+        .build()
+        .expect("must build the HTTP client")
+}
+
+/// Sends the request until it succeeds, retrying 429, 5xx and reset connections with an exponential backoff.
+///
+/// Returns the response and the number of retries, an error when the last attempt fails or the failure is
+/// not transient.
+pub fn send(http: &HttpConfig, request: impl Fn() -> RequestBuilder) -> anyhow::Result<(Response, u32)> {
+    let mut attempt = 1;
+    loop {
+        let last = attempt >= http.max_attempts;
+        let (delay, reason) = match request().send() {
+            Ok(response) if response.status().is_success() => return Ok((response, attempt - 1)),
+            Ok(response) if !last && is_transient_status(response.status()) => {
+                let delay = retry_after(&response).unwrap_or_else(|| backoff(attempt));
+                (delay, response.status().to_string())
+            }
+            Ok(response) => {
+                return Err(anyhow!(
+                    "HTTP request failed with status code {}, reason: {}",
+                    response.status(),
+                    response.text().unwrap_or("empty".to_string())
+                ))
+            }
+            Err(e) if !last && is_transient_error(&e) => (backoff(attempt), "connection failed".to_string()),
+            Err(e) => return Err(e.into()),
+        };
+        eprintln!(
+            "{} {}, retrying in {:.1}s ({}/{})",
+            "⚠️".yellow(),
+            reason.yellow(),
+            delay.as_secs_f32(),
+            attempt + 1,
+            http.max_attempts
+        );
+        std::thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// Error of a streamed response failing after its headers: the read timeout expired or the connection was reset,
+/// the completion received so far is incomplete
+pub fn stream_error(error: io::Error) -> anyhow::Error {
+    anyhow!("the response stream was interrupted: {}", error)
+}
+
+/// Reports the retries of a request as the usage of an extra event, ahead of the stream
+pub fn count_retries(retries: u32, stream: ChatStream) -> ChatStream {
+    if retries == 0 {
+        return stream;
+    }
+    let usage = TokenUsage {
+        retries,
+        ..TokenUsage::default()
+    };
+    let chunk = ChatChunk {
+        content: None,
+        usage: Some(usage),
+    };
+    Box::new(std::iter::once(Ok(chunk)).chain(stream))
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Refused or reset connections, the request may not have reached the vendor
+fn is_transient_error(error: &reqwest::Error) -> bool {
+    if error.is_connect() {
+        return true;
+    }
+    let mut source = error.source();
+    while let Some(e) = source {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            return matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof
+            );
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// `Retry-After` in seconds or as an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, SystemTime::now())
+}
+
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()?
+            .duration_since(now)
+            .unwrap_or_default(),
+    };
+    Some(delay.min(MAX_DELAY))
+}
+
+/// Exponential backoff with jitter, between half and all of `BASE_DELAY * 2^(attempt - 1)`
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY.saturating_mul(1 << (attempt - 1).min(16)).min(MAX_DELAY);
+    delay.mul_f64(0.5 + jitter() / 2.0)
+}
+
+/// Random number in `[0, 1)`, from the random keys std seeds the hash maps with
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::llm::mock::{self, MockResponse};
+
+    fn http(max_attempts: u32) -> HttpConfig {
+        HttpConfig {
+            max_attempts,
+            ..HttpConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_send_retries() {
+        let (url, server) = mock::serve(vec![
+            MockResponse::new(429, "application/json", "{}").header("Retry-After", "0"),
+            MockResponse::new(503, "application/json", "{}").header("Retry-After", "0"),
+            MockResponse::new(200, "application/json", "ok"),
+        ]);
+        let client = http_client(&http(3));
+        let (response, retries) = send(&http(3), || client.post(format!("{url}/chat"))).unwrap();
+        assert_eq!(response.text().unwrap(), "ok");
+        assert_eq!(retries, 2);
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_send_gives_up() {
+        let (url, server) = mock::serve(vec![
+            MockResponse::new(500, "application/json", "{}").header("Retry-After", "0"),
+            MockResponse::new(502, "application/json", "bad gateway"),
+        ]);
+        let client = http_client(&http(2));
+        let error = send(&http(2), || client.post(format!("{url}/chat"))).unwrap_err();
+        assert!(error.to_string().contains("502"));
+        assert!(error.to_string().contains("bad gateway"));
+        server.join().unwrap();
+
+        // client errors are not retried
+        let (url, server) = mock::serve_once(401, "application/json", "{}");
+        assert!(send(&http(3), || client.post(format!("{url}/chat"))).is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(parse_retry_after("7", now), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("3600", now), Some(MAX_DELAY));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        for attempt in 1..=10 {
+            let ceiling = BASE_DELAY.saturating_mul(1 << (attempt - 1)).min(MAX_DELAY);
+            let delay = backoff(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "{attempt}: {delay:?}");
+        }
+    }
+}
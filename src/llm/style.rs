@@ -0,0 +1,61 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Maximum number of lines of a history message used as an example
+const EXAMPLE_MAX_LINES: usize = 12;
+
+/// Commit message conventions of a repository, learned from its history
+#[derive(Debug, Default, Clone)]
+pub struct CommitStyle {
+    /// Recent commit messages, used as few-shot examples
+    pub examples: Vec<String>,
+    /// Scopes already used in the repository, most frequent first
+    pub scopes: Vec<String>,
+}
+
+impl CommitStyle {
+    /// Builds the style from commit messages, newest first
+    pub fn from_history(messages: &[String]) -> CommitStyle {
+        let re = Regex::new(r"^[A-Za-z]+\(([^)]+)\)!?:").unwrap();
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for message in messages {
+            let header = message.lines().next().unwrap_or_default();
+            if let Some(scope) = re.captures(header).and_then(|c| c.get(1)) {
+                *counts.entry(scope.as_str().trim()).or_default() += 1;
+            }
+        }
+        let mut scopes: Vec<(&str, usize)> = counts.into_iter().collect();
+        scopes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        CommitStyle {
+            examples: messages
+                .iter()
+                .map(|m| m.lines().take(EXAMPLE_MAX_LINES).collect::<Vec<_>>().join("\n"))
+                .collect(),
+            scopes: scopes.into_iter().map(|(s, _)| s.to_string()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_history() {
+        let messages = [
+            "feat(parser): support arrays",
+            "fix(cli)!: reject empty vendor\n\nBREAKING CHANGE: vendor is required",
+            "chore: bump deps",
+            "fix(parser): handle trailing commas",
+            "Merge branch 'main'",
+        ]
+        .map(|s| s.to_string());
+
+        let style = CommitStyle::from_history(&messages);
+        assert_eq!(style.scopes, vec!["parser", "cli"]);
+        assert_eq!(style.examples.len(), 5);
+        assert_eq!(style.examples[1], messages[1]);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::config::ModelParameters;
+use crate::llm::llm;
+use crate::llm::provider::{Client, TokenUsage};
+use crate::prompt::SUMMARY_PROMPT;
+use anyhow::Result;
+use colored::Colorize;
+use std::fmt::{Display, Formatter};
+
+/// How the staged diff is sent to the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DiffStrategy {
+    /// The whole diff in a single request
+    Single,
+    /// The diff is over the budget and cannot be split, it is cut to the budget and sent in a single request
+    Truncated,
+    /// The diff is summarised chunk by chunk, the commit message is built from the summaries
+    MapReduce { chunks: usize },
+}
+
+impl Display for DiffStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffStrategy::Single => write!(f, "single request"),
+            DiffStrategy::Truncated => write!(f, "single request, truncated to fit the budget"),
+            DiffStrategy::MapReduce { chunks } => write!(f, "map-reduce ({} chunks)", chunks),
+        }
+    }
+}
+
+/// Roughly estimates the number of tokens of a text, assuming 4 characters per token
+pub(crate) fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Splits a diff into chunks of at most `budget` estimated tokens.
+///
+/// The diff is split per file, files over the budget are split per hunk with the file header
+/// repeated, and hunks still over the budget are truncated.
+pub(crate) fn split_diff(diff: &str, budget: usize) -> Vec<String> {
+    let mut units = Vec::new();
+    for file in split_before(diff, "diff --git ") {
+        if estimate_tokens(&file) <= budget {
+            units.push(file);
+            continue;
+        }
+
+        let mut hunks = split_before(&file, "@@ ");
+        let header = if hunks.len() > 1 {
+            hunks.remove(0)
+        } else {
+            String::new()
+        };
+        for hunk in hunks {
+            let unit = format!("{header}{hunk}");
+            if estimate_tokens(&unit) <= budget {
+                units.push(unit);
+            } else {
+                units.push(truncate_to_budget(&unit, budget));
+            }
+        }
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for unit in units {
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(&unit) > budget {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(&unit);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Splits the text into sections, each starting with a line beginning with `marker`
+fn split_before(text: &str, marker: &str) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for line in text.split_inclusive('\n') {
+        if line.starts_with(marker) || sections.is_empty() {
+            sections.push(String::new());
+        }
+        sections.last_mut().unwrap().push_str(line);
+    }
+    sections
+}
+
+fn truncate_to_budget(text: &str, budget: usize) -> String {
+    let mut truncated = String::new();
+    for line in text.split_inclusive('\n') {
+        if estimate_tokens(&truncated) + estimate_tokens(line) > budget {
+            truncated.push_str("... (truncated)\n");
+            break;
+        }
+        truncated.push_str(line);
+    }
+    truncated
+}
+
+/// Asks the model for a summary of each chunk of the diff and joins the summaries
+pub(crate) fn summarize_diff(
+    client: &dyn Client,
+    chunks: &[String],
+    option: &ModelParameters,
+) -> Result<(String, TokenUsage)> {
+    let mut summaries = Vec::new();
+    let mut usage = TokenUsage::default();
+
+    for (idx, chunk) in chunks.iter().enumerate() {
+        eprintln!(
+            "  {} Summarising chunk {}/{}",
+            "🧩".bright_yellow(),
+            (idx + 1).to_string().bright_green(),
+            chunks.len().to_string().bright_green()
+        );
+        let messages = vec![
+            llm::Message::new_system(SUMMARY_PROMPT.to_string()),
+            llm::Message::new_user(format!("```diff\n{chunk}\n```")),
+        ];
+
+        let mut summary = String::new();
+        for data in client.stream_chat(messages, option.clone(), None)? {
+            let data = data?;
+            if let Some(content) = data.content {
+                summary.push_str(&content);
+            }
+            if let Some(u) = data.usage {
+                usage += u;
+            }
+        }
+        summaries.push(summary.trim().to_string());
+    }
+    eprintln!();
+
+    Ok((summaries.join("\n\n"), usage))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn file_diff(name: &str, hunks: &[&str]) -> String {
+        let mut diff =
+            format!("diff --git a/{name} b/{name}\nindex 1111111..2222222 100644\n--- a/{name}\n+++ b/{name}\n");
+        for hunk in hunks {
+            diff.push_str(&format!("@@ -1,1 +1,1 @@\n{hunk}\n"));
+        }
+        diff
+    }
+
+    #[test]
+    fn test_split_diff_per_file() {
+        let a = file_diff("a.rs", &["+fn a() {}"]);
+        let b = file_diff("b.rs", &["+fn b() {}"]);
+        let diff = format!("{a}{b}");
+
+        assert_eq!(split_diff(&diff, 10_000), vec![diff.clone()]);
+        assert_eq!(split_diff(&diff, estimate_tokens(&a)), vec![a, b]);
+    }
+
+    #[test]
+    fn test_split_diff_per_hunk() {
+        let hunk = format!("+{}", "x".repeat(200));
+        let diff = file_diff("big.rs", &[&hunk, &hunk]);
+        let budget = estimate_tokens(&file_diff("big.rs", &[&hunk]));
+
+        let chunks = split_diff(&diff, budget);
+        assert_eq!(chunks.len(), 2);
+        for chunk in chunks {
+            assert!(chunk.starts_with("diff --git a/big.rs b/big.rs\n"));
+            assert_eq!(chunk.matches("@@ ").count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_split_diff_truncates_large_hunk() {
+        let lines = (0..100).map(|i| format!("+line {i}")).collect::<Vec<_>>().join("\n");
+        let diff = file_diff("huge.rs", &[&lines]);
+
+        let chunks = split_diff(&diff, 100);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].ends_with("... (truncated)\n"));
+        assert!(estimate_tokens(&chunks[0]) <= 105);
+    }
+}
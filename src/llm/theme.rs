@@ -1,3 +1,4 @@
+use crate::conventional::lint::Violation;
 use crate::llm::LLMResult;
 use colored::Colorize;
 
@@ -119,24 +120,38 @@ pub fn get_commit_separator(style: u8) -> (String, String, String) {
     }
 }
 
-pub fn print_commit_options(result: &LLMResult, style: u8) {
+/// Prints the commit options, each followed by its lint violations
+pub fn print_commit_options(result: &LLMResult, violations: &[Vec<Violation>], style: u8) {
     let (header, prefix, footer) = get_commit_separator(style);
     println!("{}", header);
     for (idx, message) in result.commit_messages.iter().enumerate() {
+        println!(
+            "{}{}\n{}",
+            prefix,
+            format!("Option {}:", idx + 1).bold().bright_cyan(),
+            message.cyan()
+        );
+        for violation in violations.get(idx).into_iter().flatten() {
+            println!("  {} {}", "⚠️".bright_yellow(), violation);
+        }
+        if idx < result.commit_messages.len() - 1 {
+            println!();
+        }
+    }
+    println!("{}", footer);
+}
+
+pub fn print_commit_groups(result: &LLMResult, style: u8) {
+    let (header, prefix, footer) = get_commit_separator(style);
+    println!("{}", header);
+    for (idx, (message, files)) in result.commit_messages.iter().zip(&result.commit_files).enumerate() {
+        println!("{}{}", prefix, format!("Commit {}:", idx + 1).bold().bright_cyan());
+        for file in files {
+            println!("  {} {}", "•".bright_yellow(), file.bright_green());
+        }
+        println!("{}", message.cyan());
         if idx < result.commit_messages.len() - 1 {
-            println!(
-                "{}{}\n{}\n",
-                prefix,
-                format!("Option {}:", idx + 1).bold().bright_cyan(),
-                message.cyan()
-            );
-        } else {
-            println!(
-                "{}{}\n{}",
-                prefix,
-                format!("Option {}:", idx + 1).bold().bright_cyan(),
-                message.cyan()
-            );
+            println!();
         }
     }
     println!("{}", footer);
@@ -186,7 +201,7 @@ pub fn get_stream_separator(style: u8) -> (String, String) {
 pub fn wrap_text(text: &str, width: usize) -> String {
     let mut lines: Vec<String> = Vec::new();
     let mut current_line = String::new();
-    for l in text.split("\n").into_iter() {
+    for l in text.split('\n') {
         if l.len() <= width {
             lines.push(l.to_string());
             continue;
@@ -196,7 +211,7 @@ pub fn wrap_text(text: &str, width: usize) -> String {
         for word in words {
             if current_line.is_empty() {
                 current_line = word.to_string();
-            } else if current_line.len() + word.len() + 1 <= width {
+            } else if current_line.len() + word.len() < width {
                 current_line.push(' ');
                 current_line.push_str(word);
             } else {
@@ -204,8 +219,8 @@ pub fn wrap_text(text: &str, width: usize) -> String {
                 current_line = word.to_string();
             }
         }
-        if current_line.len() > 0 {
-            lines.push(format!("{current_line}"))
+        if !current_line.is_empty() {
+            lines.push(current_line.clone())
         }
     }
 
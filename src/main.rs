@@ -1,11 +1,17 @@
 use clap::{Parser, Subcommand};
+use conventional::lint::LintRules;
 use prompt::Prompt;
+use std::path::PathBuf;
 
 mod ai;
 mod args;
+mod changelog;
 mod config;
+mod conventional;
+mod hook;
 mod llm;
 mod prompt;
+mod redact;
 
 #[derive(Parser)]
 #[command(
@@ -29,24 +35,60 @@ struct Cli {
 
     /// prompt template, default is p1.
     /// plan to deprecate this, do not use it.
-    #[arg(long, default_value_t=Prompt::P1)]
-    prompt: Prompt,
+    #[arg(long)]
+    prompt: Option<Prompt>,
 
     /// provide a hint for the AI to generate a better commit message, eg. "type: fix, scope: core"
     #[arg(long = "hint")]
     hint: Option<String>,
 
     /// number of commit options, default is 1
-    #[arg(short = 'n', long = "number", default_value_t = 1)]
-    number_of_commit_options: u8,
+    #[arg(short = 'n', long = "number")]
+    number_of_commit_options: Option<u8>,
 
     /// the issue number or PR number, eg. #123
     #[arg(short = 'r', long = "ref")]
     reference: Option<String>,
 
     /// human-readable language, default is English
-    #[arg(short = 'l', long = "language", default_value_t = String::from("English"))]
-    language: String,
+    #[arg(short = 'l', long = "language")]
+    language: Option<String>,
+
+    /// split the staged changes into several logical commits
+    #[arg(long, default_value_t = false)]
+    split: bool,
+
+    /// number of recent commit messages used as style examples, 0 disables learning from history
+    #[arg(long, default_value_t = 0)]
+    history: usize,
+
+    /// only learn from commits touching the staged files
+    #[arg(long, default_value_t = false)]
+    history_paths: bool,
+
+    /// regenerate the message of the HEAD commit from its changes and the staged changes, and amend it
+    #[arg(long, default_value_t = false, conflicts_with = "split")]
+    amend: bool,
+
+    /// add a Signed-off-by trailer of the git user
+    #[arg(long, default_value_t = false)]
+    signoff: bool,
+
+    /// add Co-authored-by trailers, roster aliases or "Name <email>" separated by commas, eg. ana,bo
+    #[arg(long, value_delimiter = ',')]
+    pair: Vec<String>,
+
+    /// open the chosen commit message in the git editor before committing
+    #[arg(short = 'e', long, default_value_t = false, conflicts_with = "split")]
+    edit: bool,
+
+    /// accept option N (default 1) and every confirmation without reading stdin, for scripts and CI
+    #[arg(short = 'y', long, value_name = "N", num_args = 0..=1, default_missing_value = "1", conflicts_with = "edit")]
+    yes: Option<u8>,
+
+    /// print the candidates as JSON for scripts and editor plugins, nothing is committed without --yes
+    #[arg(long, value_enum, default_value_t = args::Output::Text)]
+    output: args::Output,
 }
 
 #[derive(Subcommand)]
@@ -62,14 +104,170 @@ enum Commands {
         // #[arg(long, default_value_t=String::from("deepseek"))]
         // vendor: String,
     },
+    /// Configure a vendor, or show the effective configuration
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Config {
-        #[arg(value_enum)]
-        vendor: llm::PromptModelVendor,
-        #[arg(long)]
-        api_key: String,
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
+        #[arg(value_enum, required = true)]
+        vendor: Option<llm::PromptModelVendor>,
+        #[arg(long, required = true)]
+        api_key: Option<String>,
         #[arg(long)]
         model: Option<String>,
     },
+    /// Draft the title and description of a pull request from the commits of the current branch
+    Pr {
+        /// branch the pull request is merged into, by default the upstream or the default branch of origin
+        #[arg(long)]
+        base: Option<String>,
+    },
+    /// Regenerate the message of a commit of the current branch, rewriting the commits on top of it
+    Reword {
+        /// the commit to reword, e.g. HEAD~2
+        rev: String,
+        /// test argument, generate commit message but not rewrite the commit
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    /// Regenerate the messages of the commits of `<base>..HEAD` from their own diffs, and rewrite them at once
+    #[command(name = "rewrite-range")]
+    RewriteRange {
+        /// the commits to rewrite, e.g. main..HEAD
+        range: String,
+        /// test argument, generate commit messages but not rewrite the commits
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    /// Write one commit message replacing the commits of a range when squashing them
+    #[command(name = "squash-msg")]
+    SquashMsg {
+        /// the commits to squash as `<from>..<to>`, `<to>` defaults to HEAD
+        range: String,
+    },
+    /// Write the changelog of a range of Conventional Commits in Keep a Changelog style
+    Changelog {
+        /// commits of the release as `<from>..<to>`, `<to>` defaults to HEAD
+        range: String,
+        /// let the model rewrite the entries into user-facing prose
+        #[arg(long, default_value_t = false)]
+        polish: bool,
+        /// put the release in front of the releases of a changelog file instead of printing it
+        #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "CHANGELOG.md")]
+        prepend: Option<PathBuf>,
+    },
+    /// Check commit messages against the Conventional Commits rules
+    Lint {
+        /// file holding the commit message, e.g. .git/COMMIT_EDITMSG, read from stdin when missing or `-`
+        file: Option<PathBuf>,
+        /// check the commits of a revision range instead, e.g. main..HEAD
+        #[arg(long, conflicts_with = "file")]
+        range: Option<String>,
+    },
+    /// Manage the git hook drafting a commit message on plain `git commit`
+    Hook {
+        #[command(subcommand)]
+        command: HookCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Show the effective configuration of the current repository
+    Show {
+        /// show where each value comes from: command line, .gitbuddy.toml, global config or default
+        #[arg(long, default_value_t = false)]
+        resolved: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookCommands {
+    /// Install a hook into the current repository
+    Install {
+        /// prepare-commit-msg drafts messages, commit-msg rejects messages breaking the conventions
+        #[arg(value_parser = [hook::PREPARE_COMMIT_MSG, hook::COMMIT_MSG], default_value = hook::PREPARE_COMMIT_MSG)]
+        name: String,
+        /// let the commit-msg hook rewrite rejected messages with the model
+        #[arg(long, default_value_t = false)]
+        fix: bool,
+    },
+    /// Remove a hook from the current repository
+    Uninstall {
+        #[arg(value_parser = [hook::PREPARE_COMMIT_MSG, hook::COMMIT_MSG], default_value = hook::PREPARE_COMMIT_MSG)]
+        name: String,
+    },
+    /// Run as the prepare-commit-msg hook, called by git
+    #[command(name = "prepare-commit-msg", hide = true)]
+    PrepareCommitMsg {
+        /// the commit message file
+        file: String,
+        /// the source of the commit message: message, template, merge, squash or commit
+        source: Option<String>,
+        /// the commit object name, given with amends
+        sha: Option<String>,
+    },
+    /// Run as the commit-msg hook, called by git
+    #[command(name = "commit-msg", hide = true)]
+    CommitMsg {
+        /// the commit message file
+        file: String,
+        /// rewrite rejected messages with the model
+        #[arg(long, default_value_t = false)]
+        fix: bool,
+    },
+}
+
+fn overrides(cli: &Cli) -> config::Overrides {
+    config::Overrides {
+        vendor: cli.vendor.clone(),
+        language: cli.language.clone(),
+        number: cli.number_of_commit_options,
+        prompt: cli.prompt,
+        signoff: cli.signoff.then_some(true),
+    }
+}
+
+/// Builds the command arguments from the command line layered over `.gitbuddy.toml` and the global config.
+///
+/// The trailers are only collected when the command creates new commits, `commits`, other commands work on existing
+/// commits or print messages and never sign them off.
+fn command_args(cli: &Cli, push: bool, dry_run: bool, commits: bool) -> anyhow::Result<args::CommandArgs> {
+    let resolved = config::resolve(&overrides(cli))?;
+    let trailers = if commits {
+        ai::trailers::collect(&resolved, &cli.pair)?
+    } else {
+        Vec::new()
+    };
+    Ok(args::CommandArgs::new(
+        push,
+        dry_run,
+        Some(resolved.vendor.value.clone()),
+        cli.model.clone(),
+        resolved.prompt.value,
+        cli.hint.clone(),
+        resolved.number.value,
+        cli.reference.clone(),
+        resolved.language.value.clone(),
+        cli.split,
+        cli.history,
+        cli.history_paths,
+        resolved.prompt_template()?,
+        resolved.scopes.value,
+        resolved.ignore.value,
+        cli.amend,
+        trailers,
+        cli.edit,
+        cli.yes,
+        cli.output,
+    ))
+}
+
+fn exit_on_error<T>(result: anyhow::Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    })
 }
 
 fn main() {
@@ -81,41 +279,85 @@ fn main() {
             dry_run,
             // vendor,
         }) => {
-            let cmd_args = args::CommandArgs::new(
-                *push,
-                *dry_run,
-                cli.vendor.clone(),
-                cli.model.clone(),
-                cli.prompt,
-                cli.hint.clone(),
-                cli.number_of_commit_options,
-                cli.reference.clone(),
-                cli.language,
-            );
-            ai::handler(cli.prompt, cmd_args).unwrap();
+            let cmd_args = exit_on_error(command_args(&cli, *push, *dry_run, true));
+            exit_on_error(ai::handler(cmd_args.prompt, cmd_args));
+        }
+        Some(Commands::Config {
+            command: Some(ConfigCommands::Show { resolved }),
+            ..
+        }) => {
+            exit_on_error(config::resolve(&overrides(&cli))).print(*resolved);
         }
-        Some(Commands::Config { vendor, api_key, model }) => {
-            let model = if let Some(model) = model {
-                model.to_string()
-            } else {
-                vendor.default_model().to_string()
+        Some(Commands::Config {
+            command: None,
+            vendor,
+            api_key,
+            model,
+        }) => {
+            // both are required by clap when no subcommand is given
+            let (Some(vendor), Some(api_key)) = (vendor, api_key) else {
+                unreachable!()
             };
-
-            config::handler(vendor, api_key, model).unwrap();
+            config::handler(vendor, api_key, model.clone()).unwrap();
+        }
+        Some(Commands::Pr { base }) => {
+            let cmd_args = exit_on_error(command_args(&cli, false, false, false));
+            exit_on_error(ai::pr::handler(base.as_deref(), &cmd_args));
+        }
+        Some(Commands::Reword { rev, dry_run }) => {
+            let cmd_args = exit_on_error(command_args(&cli, false, *dry_run, false));
+            exit_on_error(ai::reword::handler(rev, &cmd_args));
+        }
+        Some(Commands::RewriteRange { range, dry_run }) => {
+            let cmd_args = exit_on_error(command_args(&cli, false, *dry_run, false));
+            exit_on_error(ai::rewrite::handler(range, &cmd_args));
+        }
+        Some(Commands::SquashMsg { range }) => {
+            let cmd_args = exit_on_error(command_args(&cli, false, false, false));
+            exit_on_error(ai::squash::handler(range, &cmd_args));
+        }
+        Some(Commands::Changelog {
+            range,
+            polish,
+            prepend,
+        }) => {
+            let cmd_args = exit_on_error(command_args(&cli, false, false, false));
+            exit_on_error(ai::changelog::handler(range, *polish, prepend.as_deref(), &cmd_args));
+        }
+        Some(Commands::Lint { file, range }) => {
+            let resolved = exit_on_error(config::resolve(&overrides(&cli)));
+            let rules = LintRules::new(resolved.scopes.value);
+            exit_on_error(ai::lint::handler(file.as_deref(), range.as_deref(), &rules));
+        }
+        Some(Commands::Hook { command }) => {
+            let result = match command {
+                HookCommands::Install { name, fix } => match (*fix, name.as_str()) {
+                    (true, hook::COMMIT_MSG) => hook::install(name, &["--fix"]),
+                    (true, _) => Err(anyhow::anyhow!("--fix only applies to the {} hook", hook::COMMIT_MSG)),
+                    (false, _) => hook::install(name, &[]),
+                },
+                HookCommands::Uninstall { name } => hook::uninstall(name),
+                HookCommands::PrepareCommitMsg { file, source, sha: _ } => {
+                    // a failing prepare-commit-msg hook aborts the commit, so only report the error
+                    let result = command_args(&cli, false, false, true)
+                        .and_then(|cmd_args| hook::prepare_commit_msg(file, source.as_deref(), &cmd_args));
+                    if let Err(e) = result {
+                        eprintln!("gitbuddy: {}", e);
+                    }
+                    Ok(())
+                }
+                HookCommands::CommitMsg { file, fix } => {
+                    command_args(&cli, false, false, false).and_then(|cmd_args| hook::commit_msg(file, *fix, &cmd_args))
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
         }
         None => {
-            let cmd_args = args::CommandArgs::new(
-                false,
-                false,
-                cli.vendor.clone(),
-                cli.model.clone(),
-                cli.prompt,
-                cli.hint.clone(),
-                cli.number_of_commit_options,
-                cli.reference.clone(),
-                cli.language,
-            );
-            ai::handler(cli.prompt, cmd_args).unwrap()
+            let cmd_args = exit_on_error(command_args(&cli, false, false, true));
+            exit_on_error(ai::handler(cmd_args.prompt, cmd_args))
         }
     }
 }
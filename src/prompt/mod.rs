@@ -51,7 +51,11 @@ Generate the appropriate {{ number }} git commit messages based on the supplied
 
 ## Constraints (Must follow)
 - language of commit subject and body: {{ language }}
+- type must be one of: {{ types | join(", ") }}
 - number of commit messages: {{ number }}
+{%- if scopes %}
+- scope must be one of: {{ scopes | join(", ") }}
+{%- endif %}
 
 ## Workflow
 1. Analyze the content of the change according to the git diff context.
@@ -64,55 +68,152 @@ the object {"foo": ["bar", "baz"]} is a well-formatted instance of the schema. T
 
 Here is the output schema:
 ```json
+{{ schema }}
+```
+
+The output must be a valid JSON object with the commit messages in its `commits` array, without any other text.
+
+## Example
+```json
 {
-    "title": "Conventional Commits",
-    "description": "Generate conventional commit messages",
-    "type": "array",
-    "items": {
-        "type": "object",
-        "description": "Conventional commit message",
-        "properties": {
-            "type": {
-                "type": "string",
-                "description": "Type of current commit",
-                "enum": ["feat", "fix", "docs", "style", "refactor", "test", "chore", "ci", "revert", "build", "perf"]
-            },
-            "scope": {
-                "type": "string",
-                "description": "Affected component, e.g. auth/view"
-            },
-            "subject": {
-                "type": "string",
-                "description": "Short summary of the change, must be in imperative mood and under 80 characters, e.g. add oauth2 authentication flow"
-            },
-            "body": {
-                "type": "string",
-                "description": "Detailed description of the change"
-            },
-            "footer": {
-                "type": "string",
-                "description": "Additional information, e.g., breaking changes"
-            }
-        },
-        "required": ["type", "subject"]
-    },
-    "minItems": 1,
-    "maxItems": {{ number }}
+  "commits": [
+    {
+      "type": "feat",
+      "scope": "auth",
+      "subject": "add oauth2 authentication flow",
+      "body": "implement secure authentication using OAuth2 protocol\n- add login endpoint\n- integrate with external providers\n- handle token refresh",
+      "footer": "BREAKING CHANGE: authentication header format changed"
+    }
+  ]
 }
 ```
+{% if examples %}
+## Repository Style
+Follow the conventions of these recent commit messages of the repository, e.g. casing, scope names and body layout:
+{% for example in examples %}
+```text
+{{ example }}
+```
+{% endfor %}
+{% endif %}
+"###;
+
+pub const SUMMARY_PROMPT: &str = r###"
+### Instructions
+You are a expert software developer reviewing one part of a large git diff.
+Summarise the changes of the supplied diff so that a commit message can be written from the summaries of all parts.
 
-The output must be a valid JSON array of commit messages without any other text.
+## Constraints (Must follow)
+- list every changed file with one line per meaningful change
+- describe what changed and why it likely changed, not how the code looks
+- mention removed or renamed public APIs and breaking changes explicitly
+- plain text only, without code blocks, at most 15 lines
+"###;
+
+pub const PR_PROMPT: &str = r###"
+### Instructions
+You are a expert software developer writing the pull request of a branch for its reviewers.
+Write a pull request title and description from the supplied commit messages and diff of the branch.
+
+## Constraints (Must follow)
+- language of the title and description: {{ language }}
+- the title is a single line under 72 characters, in imperative mood, without a trailing period
+{%- if scopes %}
+- when the title follows Conventional Commits, its scope must be one of: {{ scopes | join(", ") }}
+{%- endif %}
+- the description is Markdown with exactly these sections: `## Summary`, `## Changes`, `## Testing`
+- Summary: 1 to 3 sentences on what the pull request does and why
+- Changes: a bullet list of the notable changes, grouped by area
+- Testing: how the changes are tested, derived from the tests in the diff, otherwise say they are not covered by tests
+- do not invent issue numbers, links or test results
+
+## Output Format
+The first line is the title, followed by an empty line and the description, without any other text:
+
+```markdown
+Add OAuth2 login
+
+## Summary
+...
+
+## Changes
+- ...
+
+## Testing
+- ...
+```
+"###;
+
+pub const CHANGELOG_PROMPT: &str = r###"
+### Instructions
+You are a expert technical writer preparing the release notes of a software project for its users.
+Rewrite the entries of the supplied changelog, generated from Conventional Commits, into user-facing prose.
+
+## Constraints (Must follow)
+- language of the entries: {{ language }}
+- keep the Markdown structure: the release heading, the section headings and their order, one bullet per entry
+- keep the commit hashes at the end of the entries and the bold scopes at their start
+- describe what changes for the users, not how it is implemented, in one sentence per entry
+- keep breaking changes explicit, and tell the users what they have to change
+- do not add, merge or drop entries, and do not invent features, issue numbers or links
+
+## Output Format
+Only the rewritten changelog in Markdown, without any other text or code fences.
+"###;
+
+pub const SPLIT_PROMPT: &str = r###"
+### Instructions
+You are a expert software developer and master of Conventional Commits.
+The supplied staged changes mix several unrelated changes. Split them into logical commits by intent, and generate a git commit message for each commit following the Conventional Commits specification.
+
+## Constraints (Must follow)
+- language of commit subject and body: {{ language }}
+- type must be one of: {{ types | join(", ") }}
+- every staged file must belong to exactly one commit
+- use the staged file paths exactly as they are given
+- order the commits so that each of them builds on the previous ones
+{%- if scopes %}
+- scope must be one of: {{ scopes | join(", ") }}
+{%- endif %}
+
+## Workflow
+1. Analyze the content of the change according to the git diff context.
+2. Group the staged files by the intent of their changes, e.g. a feature, a fix, a refactoring or documentation.
+3. Output one commit message per group in the format of Conventional Commits based on the following json schema.
+
+Here is the output schema:
+```json
+{{ schema }}
+```
+
+The output must be a valid JSON object with the commit messages in its `commits` array, without any other text.
 
 ## Example
 ```json
-[
-  {
-    "type": "feat",
-    "scope": "auth",
-    "subject": "add oauth2 authentication flow",
-    "body": "implement secure authentication using OAuth2 protocol\n- add login endpoint\n- integrate with external providers\n- handle token refresh",
-    "footer": "BREAKING CHANGE: authentication header format changed"
-  }
-]
+{
+  "commits": [
+    {
+      "type": "feat",
+      "scope": "auth",
+      "subject": "add oauth2 authentication flow",
+      "body": "implement secure authentication using OAuth2 protocol",
+      "files": ["src/auth/oauth2.rs", "src/auth/mod.rs"]
+    },
+    {
+      "type": "docs",
+      "subject": "fix typos in contributing guide",
+      "files": ["CONTRIBUTING.md"]
+    }
+  ]
+}
+```
+{% if examples %}
+## Repository Style
+Follow the conventions of these recent commit messages of the repository, e.g. casing, scope names and body layout:
+{% for example in examples %}
+```text
+{{ example }}
 ```
+{% endfor %}
+{% endif %}
 "###;